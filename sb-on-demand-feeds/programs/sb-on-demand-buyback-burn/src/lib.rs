@@ -0,0 +1,283 @@
+use anchor_lang::prelude::*;
+use std::cell::Ref;
+use anchor_spl::token::{self, Mint, Token, TokenAccount};
+use switchboard_on_demand::on_demand::accounts::pull_feed::PullFeedAccountData;
+use switchboard_on_demand::program_id::{ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
+
+declare_id!("Cx4pMXPX2eqtXLP5zYy4NDBNRmQocmLuZfBpLuiMCr5o");
+
+/// Parse a pull feed account, rejecting accounts not owned by Switchboard
+/// and accounts too short to hold one instead of letting the SDK panic on
+/// truncated data.
+pub fn load_feed<'a>(feed: &'a AccountInfo) -> Result<Ref<'a, PullFeedAccountData>> {
+    check_feed_owner(feed)?;
+    let feed_account = feed.data.borrow();
+    let min_len = 8 + std::mem::size_of::<PullFeedAccountData>();
+    if feed_account.len() < min_len {
+        msg!("Feed account holds {} bytes, expected at least {}", feed_account.len(), min_len);
+        return Err(ErrorCode::InvalidFeedAccount.into());
+    }
+    PullFeedAccountData::parse(feed_account).map_err(|e| {
+        msg!("Parse Error: {:?}", e);
+        ErrorCode::InvalidFeedAccount.into()
+    })
+}
+
+/// Parse a pull feed and return its median value as an 18-decimal fixed point
+/// integer, rejecting results older than `max_stale_slots`.
+pub fn feed_value(feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+    let feed = load_feed(feed)?;
+    // A result newer than the clock usually means a local test validator was
+    // never advanced; report it separately from ordinary staleness.
+    if feed.result.slot > clock.slot {
+        msg!(
+            "Feed result slot {} is ahead of the current slot {}; is the test clock behind?",
+            feed.result.slot,
+            clock.slot
+        );
+        return Err(ErrorCode::FeedFromFutureSlot.into());
+    }
+    let value = feed
+        .get_value(clock, max_stale_slots, 1, false)
+        .map_err(|e| {
+            msg!("Get Value Error: {:?}", e);
+            ErrorCode::StaleFeed
+        })?;
+    Ok(value.mantissa())
+}
+
+/// Reject accounts that are not owned by the Switchboard On-Demand program.
+pub fn check_feed_owner(feed: &AccountInfo) -> Result<()> {
+    if feed.owner != &ON_DEMAND_MAINNET_PID && feed.owner != &ON_DEMAND_DEVNET_PID {
+        msg!("Feed {} is owned by {}", feed.key(), feed.owner);
+        return Err(ErrorCode::InvalidFeedOwner.into());
+    }
+    Ok(())
+}
+
+/// Parse a pull feed and return the slot its current result was produced at.
+pub fn feed_result_slot(feed: &AccountInfo) -> Result<u64> {
+    let feed = load_feed(feed)?;
+    Ok(feed.result.slot)
+}
+
+// 10^18, the fixed point scale of feed values.
+pub const SCALE: i128 = 1_000_000_000_000_000_000;
+
+#[program]
+pub mod sb_on_demand_buyback_burn {
+    use super::*;
+
+    pub fn init_burn_config(ctx: Context<InitBurnConfig>, floor: i128, burn_amount: u64) -> Result<()> {
+        check_feed_owner(&ctx.accounts.feed)?;
+        let config = &mut ctx.accounts.burn_config;
+        config.authority = ctx.accounts.authority.key();
+        config.feed = ctx.accounts.feed.key();
+        config.mint = ctx.accounts.mint.key();
+        config.floor = floor;
+        config.burn_amount = burn_amount;
+        config.bump = ctx.bumps.burn_config;
+        Ok(())
+    }
+
+    // Burn `burn_amount` tokens from the treasury when the feed price is below
+    // the floor. Each feed result can trigger at most one burn.
+    pub fn burn_below_floor(ctx: Context<BurnBelowFloor>, max_stale_slots: u64) -> Result<()> {
+        let config = &mut ctx.accounts.burn_config;
+        let price = config.trigger(&ctx.accounts.feed, &Clock::get()?, max_stale_slots)?;
+
+        let mint_key = config.mint;
+        let seeds: &[&[u8]] = &[b"burnConfig".as_ref(), mint_key.as_ref(), &[config.bump]];
+        token::burn(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Burn {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    from: ctx.accounts.treasury.to_account_info(),
+                    authority: ctx.accounts.burn_config.to_account_info(),
+                },
+                &[seeds],
+            ),
+            ctx.accounts.burn_config.burn_amount,
+        )?;
+        msg!("Burned {} at price {}", ctx.accounts.burn_config.burn_amount, price);
+        Ok(())
+    }
+}
+
+// === Accounts ===
+#[account]
+pub struct BurnConfig {
+    pub authority: Pubkey,
+    pub feed: Pubkey,
+    pub mint: Pubkey,
+    pub floor: i128,         // 18 decimals; burns trigger below this price
+    pub burn_amount: u64,    // Tokens burned per trigger
+    pub last_burn_slot: u64, // Result slot of the last burn
+    pub bump: u8,
+}
+
+impl BurnConfig {
+    // Claim the current feed result for a burn, returning its price. Fails
+    // unless the price is below the floor and the result has not burned yet.
+    pub fn trigger(&mut self, feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+        let price = feed_value(feed, clock, max_stale_slots)?;
+        let result_slot = feed_result_slot(feed)?;
+        if price >= self.floor {
+            msg!("price {} is not below the floor {}", price, self.floor);
+            return Err(ErrorCode::PriceAboveFloor.into());
+        }
+        require!(result_slot > self.last_burn_slot, ErrorCode::DuplicateResult);
+        self.last_burn_slot = result_slot;
+        Ok(price)
+    }
+}
+
+// === Instructions ===
+#[derive(Accounts)]
+pub struct InitBurnConfig<'info> {
+    #[account(init,
+        payer = authority,
+        seeds = [b"burnConfig".as_ref(), mint.key().as_ref()],
+        space = 8 + 32 + 32 + 32 + 16 + 8 + 8 + 1,
+        bump)]
+    pub burn_config: Account<'info, BurnConfig>,
+    #[account(init,
+        payer = authority,
+        seeds = [b"burnTreasury".as_ref(), mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = burn_config)]
+    pub treasury: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BurnBelowFloor<'info> {
+    #[account(mut,
+        seeds = [b"burnConfig".as_ref(), mint.key().as_ref()],
+        bump = burn_config.bump,
+        has_one = mint,
+        has_one = feed)]
+    pub burn_config: Account<'info, BurnConfig>,
+    #[account(mut, seeds = [b"burnTreasury".as_ref(), mint.key().as_ref()], bump)]
+    pub treasury: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+// === Errors ===
+#[error_code]
+pub enum ErrorCode {
+    #[msg("The account is not a valid Switchboard pull feed.")]
+    InvalidFeedAccount,
+    #[msg("The feed value is stale.")]
+    StaleFeed,
+    #[msg("The feed account is not owned by the Switchboard program.")]
+    InvalidFeedOwner,
+    #[msg("The feed result is from a slot ahead of the current clock.")]
+    FeedFromFutureSlot,
+    #[msg("This feed result has already been recorded.")]
+    DuplicateResult,
+    #[msg("The price is not below the configured floor.")]
+    PriceAboveFloor,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(whole: i128) -> i128 {
+        whole * SCALE
+    }
+
+    // Slot of the test clock; fixture results are produced a few slots before it.
+    const NOW: u64 = 1_000;
+
+    fn clock_at(slot: u64) -> Clock {
+        Clock { slot, ..Clock::default() }
+    }
+
+    /// Backing storage for a Switchboard pull feed account whose only oracle
+    /// reported `value` at `slot`, owned by the on-demand program.
+    struct TestFeed {
+        key: Pubkey,
+        lamports: u64,
+        buf: Vec<u8>,
+        start: usize,
+    }
+
+    impl TestFeed {
+        fn new(value: i128, slot: u64) -> Self {
+            Self::with(value, slot, |_| {})
+        }
+
+        /// Like `new`, letting `edit` adjust the account before it is written.
+        fn with(value: i128, slot: u64, edit: impl FnOnce(&mut PullFeedAccountData)) -> Self {
+            // SAFETY: the account is a zero-copy plain-old-data struct, for
+            // which all zero bytes are a valid value.
+            let mut feed: PullFeedAccountData = unsafe { std::mem::zeroed() };
+            feed.result.value = value;
+            feed.result.slot = slot;
+            feed.submissions[0].oracle = Pubkey::new_unique();
+            feed.submissions[0].slot = slot;
+            feed.submissions[0].value = value;
+            edit(&mut feed);
+            // SAFETY: as above, the struct has no padding-sensitive invariants.
+            let bytes = unsafe {
+                std::slice::from_raw_parts(
+                    &feed as *const PullFeedAccountData as *const u8,
+                    std::mem::size_of::<PullFeedAccountData>(),
+                )
+            };
+            // The SDK casts the bytes after the discriminator in place, so they
+            // must be 16-byte aligned for the i128 fields.
+            let mut buf = vec![0u8; 8 + bytes.len() + 16];
+            let start = (16 - (buf.as_ptr() as usize + 8) % 16) % 16;
+            let discriminator = anchor_lang::solana_program::hash::hash(b"account:PullFeedAccountData");
+            buf[start..start + 8].copy_from_slice(&discriminator.to_bytes()[..8]);
+            buf[start + 8..start + 8 + bytes.len()].copy_from_slice(bytes);
+            Self { key: Pubkey::new_unique(), lamports: 1, buf, start }
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            let len = 8 + std::mem::size_of::<PullFeedAccountData>();
+            let data = &mut self.buf[self.start..self.start + len];
+            AccountInfo::new(&self.key, false, false, &mut self.lamports, data, &ON_DEMAND_MAINNET_PID, false, 0)
+        }
+    }
+
+    #[test]
+    fn burn_triggers_once_per_result_below_the_floor() {
+        let mut config = BurnConfig {
+            authority: Pubkey::new_unique(),
+            feed: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            floor: price(1),
+            burn_amount: 1_000,
+            last_burn_slot: 0,
+            bump: 255,
+        };
+        let clock = clock_at(NOW);
+        let mut above = TestFeed::new(price(1), NOW - 2);
+        assert_eq!(config.trigger(&above.info(), &clock, 10).unwrap_err(), Error::from(ErrorCode::PriceAboveFloor));
+        assert_eq!(config.last_burn_slot, 0);
+
+        let mut below = TestFeed::new(price(1) * 9 / 10, NOW - 1);
+        assert_eq!(config.trigger(&below.info(), &clock, 10).unwrap(), price(1) * 9 / 10);
+        assert_eq!(config.last_burn_slot, NOW - 1);
+        assert_eq!(config.trigger(&below.info(), &clock, 10).unwrap_err(), Error::from(ErrorCode::DuplicateResult));
+        let mut stale = TestFeed::new(price(1) / 2, NOW - 30);
+        assert_eq!(config.trigger(&stale.info(), &clock, 10).unwrap_err(), Error::from(ErrorCode::StaleFeed));
+        assert_eq!(config.last_burn_slot, NOW - 1);
+    }
+}
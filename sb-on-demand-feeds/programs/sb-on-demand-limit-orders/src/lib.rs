@@ -0,0 +1,306 @@
+use anchor_lang::prelude::*;
+use std::cell::Ref;
+use switchboard_on_demand::on_demand::accounts::pull_feed::PullFeedAccountData;
+use switchboard_on_demand::program_id::{ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
+
+declare_id!("CWgDFK6kvoPQZEuNtR9GL81dyuf28ycTosLaAPH8R4xW");
+
+/// Parse a pull feed account, rejecting accounts not owned by Switchboard
+/// and accounts too short to hold one instead of letting the SDK panic on
+/// truncated data.
+pub fn load_feed<'a>(feed: &'a AccountInfo) -> Result<Ref<'a, PullFeedAccountData>> {
+    check_feed_owner(feed)?;
+    let feed_account = feed.data.borrow();
+    let min_len = 8 + std::mem::size_of::<PullFeedAccountData>();
+    if feed_account.len() < min_len {
+        msg!("Feed account holds {} bytes, expected at least {}", feed_account.len(), min_len);
+        return Err(ErrorCode::InvalidFeedAccount.into());
+    }
+    PullFeedAccountData::parse(feed_account).map_err(|e| {
+        msg!("Parse Error: {:?}", e);
+        ErrorCode::InvalidFeedAccount.into()
+    })
+}
+
+/// Parse a pull feed and return its median value as an 18-decimal fixed point
+/// integer, rejecting results older than `max_stale_slots`.
+pub fn feed_value(feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+    let feed = load_feed(feed)?;
+    // A result newer than the clock usually means a local test validator was
+    // never advanced; report it separately from ordinary staleness.
+    if feed.result.slot > clock.slot {
+        msg!(
+            "Feed result slot {} is ahead of the current slot {}; is the test clock behind?",
+            feed.result.slot,
+            clock.slot
+        );
+        return Err(ErrorCode::FeedFromFutureSlot.into());
+    }
+    let value = feed
+        .get_value(clock, max_stale_slots, 1, false)
+        .map_err(|e| {
+            msg!("Get Value Error: {:?}", e);
+            ErrorCode::StaleFeed
+        })?;
+    Ok(value.mantissa())
+}
+
+/// Reject accounts that are not owned by the Switchboard On-Demand program.
+pub fn check_feed_owner(feed: &AccountInfo) -> Result<()> {
+    if feed.owner != &ON_DEMAND_MAINNET_PID && feed.owner != &ON_DEMAND_DEVNET_PID {
+        msg!("Feed {} is owned by {}", feed.key(), feed.owner);
+        return Err(ErrorCode::InvalidFeedOwner.into());
+    }
+    Ok(())
+}
+
+// 10^18, the fixed point scale of feed values.
+pub const SCALE: i128 = 1_000_000_000_000_000_000;
+
+#[program]
+pub mod sb_on_demand_limit_orders {
+    use super::*;
+
+    // Place a limit order, recording the feed price at placement for reference.
+    pub fn place_order(
+        ctx: Context<PlaceOrder>,
+        id: u64,
+        limit_price: i128,
+        is_buy: bool,
+        amount: u64,
+        max_stale_slots: u64,
+    ) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        order.owner = ctx.accounts.owner.key();
+        order.feed = ctx.accounts.feed.key();
+        order.id = id;
+        order.limit_price = limit_price;
+        order.is_buy = is_buy;
+        order.amount = amount;
+        order.bump = ctx.bumps.order;
+        order.place(&ctx.accounts.feed, &Clock::get()?, max_stale_slots)?;
+        msg!("Order {} placed at spot {}", id, order.placed_price);
+        Ok(())
+    }
+
+    // Fill the order once the current feed price crosses its limit: at or below
+    // the limit for buys, at or above it for sells. Stale prices are rejected.
+    pub fn fill_order(ctx: Context<FillOrder>, max_stale_slots: u64) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+        let price = order.fill(&ctx.accounts.feed, &Clock::get()?, max_stale_slots)?;
+        // Settlement of `amount` goes here; this example only records the fill.
+        msg!("Order {} filled at {}", order.id, price);
+        Ok(())
+    }
+}
+
+// === Accounts ===
+#[account]
+pub struct Order {
+    pub owner: Pubkey,
+    pub feed: Pubkey,
+    pub id: u64,
+    pub limit_price: i128, // 18 decimals
+    pub is_buy: bool,
+    pub amount: u64,
+    pub placed_price: i128, // Spot price when the order was placed
+    pub placed_slot: u64,
+    pub filled: bool,
+    pub fill_price: i128,
+    pub fill_slot: u64,
+    pub bump: u8,
+}
+
+impl Order {
+    // Record the current feed price as the spot at placement.
+    pub fn place(&mut self, feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<()> {
+        self.placed_price = feed_value(feed, clock, max_stale_slots)?;
+        self.placed_slot = clock.slot;
+        Ok(())
+    }
+
+    // Mark the order filled at the current feed price if it crosses the limit:
+    // at or below it for buys, at or above it for sells.
+    pub fn fill(&mut self, feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+        let price = feed_value(feed, clock, max_stale_slots)?;
+        require!(!self.filled, ErrorCode::OrderAlreadyFilled);
+        let crossed = if self.is_buy {
+            price <= self.limit_price
+        } else {
+            price >= self.limit_price
+        };
+        if !crossed {
+            msg!("price: {}, limit: {}", price, self.limit_price);
+            return Err(ErrorCode::LimitNotReached.into());
+        }
+        self.filled = true;
+        self.fill_price = price;
+        self.fill_slot = clock.slot;
+        Ok(price)
+    }
+}
+
+// === Instructions ===
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct PlaceOrder<'info> {
+    #[account(init,
+        payer = owner,
+        seeds = [b"order".as_ref(), owner.key().as_ref(), id.to_le_bytes().as_ref()],
+        space = 8 + 32 + 32 + 8 + 16 + 1 + 8 + 16 + 8 + 1 + 16 + 8 + 1,
+        bump)]
+    pub order: Account<'info, Order>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FillOrder<'info> {
+    #[account(mut,
+        seeds = [b"order".as_ref(), order.owner.as_ref(), order.id.to_le_bytes().as_ref()],
+        bump = order.bump,
+        has_one = feed)]
+    pub order: Account<'info, Order>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+// === Errors ===
+#[error_code]
+pub enum ErrorCode {
+    #[msg("The account is not a valid Switchboard pull feed.")]
+    InvalidFeedAccount,
+    #[msg("The feed value is stale.")]
+    StaleFeed,
+    #[msg("The feed account is not owned by the Switchboard program.")]
+    InvalidFeedOwner,
+    #[msg("The feed result is from a slot ahead of the current clock.")]
+    FeedFromFutureSlot,
+    #[msg("The order has already been filled.")]
+    OrderAlreadyFilled,
+    #[msg("The feed price has not crossed the order's limit.")]
+    LimitNotReached,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(whole: i128) -> i128 {
+        whole * SCALE
+    }
+
+    // Slot of the test clock; fixture results are produced a few slots before it.
+    const NOW: u64 = 1_000;
+
+    fn clock_at(slot: u64) -> Clock {
+        Clock { slot, ..Clock::default() }
+    }
+
+    /// Backing storage for a Switchboard pull feed account whose only oracle
+    /// reported `value` at `slot`, owned by the on-demand program.
+    struct TestFeed {
+        key: Pubkey,
+        lamports: u64,
+        buf: Vec<u8>,
+        start: usize,
+    }
+
+    impl TestFeed {
+        fn new(value: i128, slot: u64) -> Self {
+            Self::with(value, slot, |_| {})
+        }
+
+        /// Like `new`, letting `edit` adjust the account before it is written.
+        fn with(value: i128, slot: u64, edit: impl FnOnce(&mut PullFeedAccountData)) -> Self {
+            // SAFETY: the account is a zero-copy plain-old-data struct, for
+            // which all zero bytes are a valid value.
+            let mut feed: PullFeedAccountData = unsafe { std::mem::zeroed() };
+            feed.result.value = value;
+            feed.result.slot = slot;
+            feed.submissions[0].oracle = Pubkey::new_unique();
+            feed.submissions[0].slot = slot;
+            feed.submissions[0].value = value;
+            edit(&mut feed);
+            // SAFETY: as above, the struct has no padding-sensitive invariants.
+            let bytes = unsafe {
+                std::slice::from_raw_parts(
+                    &feed as *const PullFeedAccountData as *const u8,
+                    std::mem::size_of::<PullFeedAccountData>(),
+                )
+            };
+            // The SDK casts the bytes after the discriminator in place, so they
+            // must be 16-byte aligned for the i128 fields.
+            let mut buf = vec![0u8; 8 + bytes.len() + 16];
+            let start = (16 - (buf.as_ptr() as usize + 8) % 16) % 16;
+            let discriminator = anchor_lang::solana_program::hash::hash(b"account:PullFeedAccountData");
+            buf[start..start + 8].copy_from_slice(&discriminator.to_bytes()[..8]);
+            buf[start + 8..start + 8 + bytes.len()].copy_from_slice(bytes);
+            Self { key: Pubkey::new_unique(), lamports: 1, buf, start }
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            let len = 8 + std::mem::size_of::<PullFeedAccountData>();
+            let data = &mut self.buf[self.start..self.start + len];
+            AccountInfo::new(&self.key, false, false, &mut self.lamports, data, &ON_DEMAND_MAINNET_PID, false, 0)
+        }
+    }
+
+    fn limit_order(limit_price: i128, is_buy: bool) -> Order {
+        Order {
+            owner: Pubkey::new_unique(),
+            feed: Pubkey::new_unique(),
+            id: 1,
+            limit_price,
+            is_buy,
+            amount: 10,
+            placed_price: 0,
+            placed_slot: 0,
+            filled: false,
+            fill_price: 0,
+            fill_slot: 0,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn buy_order_fills_once_the_price_crosses() {
+        let mut order = limit_order(price(95), true);
+        let mut spot = TestFeed::new(price(100), NOW);
+        order.place(&spot.info(), &clock_at(NOW), 10).unwrap();
+        assert_eq!((order.placed_price, order.placed_slot), (price(100), NOW));
+
+        // Still above the buy limit.
+        let mut above = TestFeed::new(price(96), NOW + 5);
+        assert_eq!(
+            order.fill(&above.info(), &clock_at(NOW + 5), 10).unwrap_err(),
+            Error::from(ErrorCode::LimitNotReached)
+        );
+        // Crossed below the limit.
+        let mut crossed = TestFeed::new(price(94), NOW + 9);
+        assert_eq!(order.fill(&crossed.info(), &clock_at(NOW + 9), 10).unwrap(), price(94));
+        assert!(order.filled);
+        assert_eq!((order.fill_price, order.fill_slot), (price(94), NOW + 9));
+        assert_eq!(
+            order.fill(&crossed.info(), &clock_at(NOW + 9), 10).unwrap_err(),
+            Error::from(ErrorCode::OrderAlreadyFilled)
+        );
+    }
+
+    #[test]
+    fn sell_order_is_not_filled_at_a_stale_crossing_price() {
+        let mut order = limit_order(price(105), false);
+        // The last result crossed the limit, but 30 slots ago.
+        let mut stale = TestFeed::new(price(110), NOW - 30);
+        assert_eq!(
+            order.fill(&stale.info(), &clock_at(NOW), 10).unwrap_err(),
+            Error::from(ErrorCode::StaleFeed)
+        );
+        assert!(!order.filled);
+        let mut fresh = TestFeed::new(price(105), NOW);
+        assert_eq!(order.fill(&fresh.info(), &clock_at(NOW), 10).unwrap(), price(105));
+    }
+}
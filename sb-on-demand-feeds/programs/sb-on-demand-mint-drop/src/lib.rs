@@ -0,0 +1,293 @@
+use anchor_lang::prelude::*;
+use std::cell::Ref;
+use anchor_spl::token::{self, Mint, Token, TokenAccount};
+use switchboard_on_demand::on_demand::accounts::pull_feed::PullFeedAccountData;
+use switchboard_on_demand::program_id::{ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
+
+declare_id!("GXvijczopKJjueSnZAcPe2ycCXf1wUMr7MF2t5THWi7B");
+
+/// Parse a pull feed account, rejecting accounts not owned by Switchboard
+/// and accounts too short to hold one instead of letting the SDK panic on
+/// truncated data.
+pub fn load_feed<'a>(feed: &'a AccountInfo) -> Result<Ref<'a, PullFeedAccountData>> {
+    check_feed_owner(feed)?;
+    let feed_account = feed.data.borrow();
+    let min_len = 8 + std::mem::size_of::<PullFeedAccountData>();
+    if feed_account.len() < min_len {
+        msg!("Feed account holds {} bytes, expected at least {}", feed_account.len(), min_len);
+        return Err(ErrorCode::InvalidFeedAccount.into());
+    }
+    PullFeedAccountData::parse(feed_account).map_err(|e| {
+        msg!("Parse Error: {:?}", e);
+        ErrorCode::InvalidFeedAccount.into()
+    })
+}
+
+/// Parse a pull feed and return its median value as an 18-decimal fixed point
+/// integer, rejecting results older than `max_stale_slots`.
+pub fn feed_value(feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+    let feed = load_feed(feed)?;
+    // A result newer than the clock usually means a local test validator was
+    // never advanced; report it separately from ordinary staleness.
+    if feed.result.slot > clock.slot {
+        msg!(
+            "Feed result slot {} is ahead of the current slot {}; is the test clock behind?",
+            feed.result.slot,
+            clock.slot
+        );
+        return Err(ErrorCode::FeedFromFutureSlot.into());
+    }
+    let value = feed
+        .get_value(clock, max_stale_slots, 1, false)
+        .map_err(|e| {
+            msg!("Get Value Error: {:?}", e);
+            ErrorCode::StaleFeed
+        })?;
+    Ok(value.mantissa())
+}
+
+/// Reject accounts that are not owned by the Switchboard On-Demand program.
+pub fn check_feed_owner(feed: &AccountInfo) -> Result<()> {
+    if feed.owner != &ON_DEMAND_MAINNET_PID && feed.owner != &ON_DEMAND_DEVNET_PID {
+        msg!("Feed {} is owned by {}", feed.key(), feed.owner);
+        return Err(ErrorCode::InvalidFeedOwner.into());
+    }
+    Ok(())
+}
+
+// 10^18, the fixed point scale of feed values.
+pub const SCALE: i128 = 1_000_000_000_000_000_000;
+
+/// `a * b / d` for unsigned values, computed with a 256-bit intermediate so
+/// the product cannot overflow. Returns None if `d` is zero or the quotient
+/// does not fit in a u128.
+fn mul_div_u128(a: u128, b: u128, d: u128) -> Option<u128> {
+    if d == 0 {
+        return None;
+    }
+    // 128 x 128 -> 256 bit product from 64-bit limbs.
+    const MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_hi = a_hi * b_hi;
+    let cross = (lo_lo >> 64) + (hi_lo & MASK) + (lo_hi & MASK);
+    let lo = (cross << 64) | (lo_lo & MASK);
+    let hi = hi_hi + (hi_lo >> 64) + (lo_hi >> 64) + (cross >> 64);
+    if hi >= d {
+        return None;
+    }
+    // Shift-subtract long division of hi:lo by d; the remainder stays below d.
+    let mut remainder = hi;
+    let mut quotient = 0u128;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> bit) & 1);
+        if carry == 1 || remainder >= d {
+            remainder = remainder.wrapping_sub(d);
+            quotient |= 1 << bit;
+        }
+    }
+    Some(quotient)
+}
+
+/// `a * b / d`, truncated toward zero like `/`, without overflowing on the
+/// intermediate product. Use it to rescale 18-decimal values, e.g.
+/// `mul_div(numerator, SCALE, denominator)` for a fixed point ratio.
+pub fn mul_div(a: i128, b: i128, d: i128) -> Result<i128> {
+    require!(d != 0, ErrorCode::DivideByZero);
+    let negative = (a < 0) ^ (b < 0) ^ (d < 0);
+    let magnitude = mul_div_u128(a.unsigned_abs(), b.unsigned_abs(), d.unsigned_abs())
+        .ok_or(ErrorCode::MathOverflow)?;
+    if negative {
+        0i128.checked_sub_unsigned(magnitude).ok_or(ErrorCode::MathOverflow.into())
+    } else {
+        i128::try_from(magnitude).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+}
+
+#[program]
+pub mod sb_on_demand_mint_drop {
+    use super::*;
+
+    // Create a drop whose items cost `price_usd` (18 decimals), paid in SOL at
+    // the feed's SOL/USD price. Minting is only open while that price lies
+    // within [min_sol_price, max_sol_price].
+    pub fn init_drop(
+        ctx: Context<InitDrop>,
+        price_usd: i128,
+        min_sol_price: i128,
+        max_sol_price: i128,
+    ) -> Result<()> {
+        require!(price_usd > 0 && min_sol_price > 0, ErrorCode::InvalidPriceRange);
+        require!(min_sol_price <= max_sol_price, ErrorCode::InvalidPriceRange);
+        let drop = &mut ctx.accounts.drop;
+        drop.authority = ctx.accounts.authority.key();
+        drop.feed = ctx.accounts.feed.key();
+        drop.mint = ctx.accounts.mint.key();
+        drop.price_usd = price_usd;
+        drop.min_sol_price = min_sol_price;
+        drop.max_sol_price = max_sol_price;
+        drop.bump = ctx.bumps.drop;
+        Ok(())
+    }
+
+    // Charge the buyer the SOL equivalent of the drop's USD price and mint them one item.
+    pub fn mint_from_drop(ctx: Context<MintFromDrop>, max_stale_slots: u64) -> Result<()> {
+        let sol_price = feed_value(&ctx.accounts.feed, &Clock::get()?, max_stale_slots)?;
+        let drop = &ctx.accounts.drop;
+        let lamports = drop.price_lamports(sol_price)?;
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.buyer.to_account_info(),
+                    to: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            lamports,
+        )?;
+
+        let mint_key = drop.mint;
+        let seeds: &[&[u8]] = &[b"drop".as_ref(), mint_key.as_ref(), &[drop.bump]];
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::MintTo {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.buyer_tokens.to_account_info(),
+                    authority: ctx.accounts.drop.to_account_info(),
+                },
+                &[seeds],
+            ),
+            1,
+        )?;
+        msg!("Minted for {} lamports at SOL price {}", lamports, sol_price);
+        Ok(())
+    }
+}
+
+// === Accounts ===
+#[account]
+pub struct MintDrop {
+    pub authority: Pubkey, // Receives mint payments
+    pub feed: Pubkey,      // SOL/USD feed
+    pub mint: Pubkey,
+    pub price_usd: i128,     // 18 decimals
+    pub min_sol_price: i128, // 18 decimals
+    pub max_sol_price: i128, // 18 decimals
+    pub bump: u8,
+}
+
+impl MintDrop {
+    /// Lamports the drop's USD price costs at `sol_price`, rejected outside
+    /// the drop's accepted SOL price range.
+    pub fn price_lamports(&self, sol_price: i128) -> Result<u64> {
+        if sol_price < self.min_sol_price || sol_price > self.max_sol_price {
+            msg!("SOL price {} outside [{}, {}]", sol_price, self.min_sol_price, self.max_sol_price);
+            return Err(ErrorCode::PriceOutOfRange.into());
+        }
+        let lamports = mul_div(
+            self.price_usd,
+            anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL as i128,
+            sol_price,
+        )?;
+        u64::try_from(lamports).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+}
+
+// === Instructions ===
+#[derive(Accounts)]
+pub struct InitDrop<'info> {
+    #[account(init,
+        payer = authority,
+        seeds = [b"drop".as_ref(), mint.key().as_ref()],
+        space = 8 + 32 + 32 + 32 + 16 + 16 + 16 + 1,
+        bump)]
+    pub drop: Account<'info, MintDrop>,
+    #[account(init,
+        payer = authority,
+        mint::decimals = 0,
+        mint::authority = drop)]
+    pub mint: Account<'info, Mint>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MintFromDrop<'info> {
+    #[account(seeds = [b"drop".as_ref(), mint.key().as_ref()],
+        bump = drop.bump,
+        has_one = mint,
+        has_one = feed,
+        has_one = authority)]
+    pub drop: Account<'info, MintDrop>,
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    #[account(mut, token::mint = mint, token::authority = buyer)]
+    pub buyer_tokens: Account<'info, TokenAccount>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    /// CHECK: Checked against the drop's authority.
+    #[account(mut)]
+    pub authority: AccountInfo<'info>,
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+// === Errors ===
+#[error_code]
+pub enum ErrorCode {
+    #[msg("The account is not a valid Switchboard pull feed.")]
+    InvalidFeedAccount,
+    #[msg("The feed value is stale.")]
+    StaleFeed,
+    #[msg("The feed account is not owned by the Switchboard program.")]
+    InvalidFeedOwner,
+    #[msg("Arithmetic overflow.")]
+    MathOverflow,
+    #[msg("The feed result is from a slot ahead of the current clock.")]
+    FeedFromFutureSlot,
+    #[msg("Division by zero.")]
+    DivideByZero,
+    #[msg("The price range is invalid.")]
+    InvalidPriceRange,
+    #[msg("The feed price is outside the accepted range.")]
+    PriceOutOfRange,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(whole: i128) -> i128 {
+        whole * SCALE
+    }
+
+    #[test]
+    fn drop_price_follows_the_feed() {
+        let drop = MintDrop {
+            authority: Pubkey::new_unique(),
+            feed: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            price_usd: price(100),
+            min_sol_price: price(10),
+            max_sol_price: price(1_000),
+            bump: 0,
+        };
+        // A $100 item costs 2 SOL at $50 and 0.5 SOL at $200.
+        assert_eq!(drop.price_lamports(price(50)).unwrap(), 2_000_000_000);
+        assert_eq!(drop.price_lamports(price(200)).unwrap(), 500_000_000);
+        assert_eq!(drop.price_lamports(price(5)).unwrap_err(), Error::from(ErrorCode::PriceOutOfRange));
+        assert_eq!(drop.price_lamports(price(2_000)).unwrap_err(), Error::from(ErrorCode::PriceOutOfRange));
+    }
+}
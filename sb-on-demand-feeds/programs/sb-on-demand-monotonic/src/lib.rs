@@ -0,0 +1,138 @@
+use anchor_lang::prelude::*;
+use std::cell::Ref;
+use switchboard_on_demand::on_demand::accounts::pull_feed::PullFeedAccountData;
+use switchboard_on_demand::program_id::{ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
+
+declare_id!("FWaUPG7yow1DAzLb7EueRerX31KSq41tQ5kkPgkqmEZB");
+
+/// Parse a pull feed account, rejecting accounts not owned by Switchboard
+/// and accounts too short to hold one instead of letting the SDK panic on
+/// truncated data.
+pub fn load_feed<'a>(feed: &'a AccountInfo) -> Result<Ref<'a, PullFeedAccountData>> {
+    check_feed_owner(feed)?;
+    let feed_account = feed.data.borrow();
+    let min_len = 8 + std::mem::size_of::<PullFeedAccountData>();
+    if feed_account.len() < min_len {
+        msg!("Feed account holds {} bytes, expected at least {}", feed_account.len(), min_len);
+        return Err(ErrorCode::InvalidFeedAccount.into());
+    }
+    PullFeedAccountData::parse(feed_account).map_err(|e| {
+        msg!("Parse Error: {:?}", e);
+        ErrorCode::InvalidFeedAccount.into()
+    })
+}
+
+/// Parse a pull feed and return its median value as an 18-decimal fixed point
+/// integer, rejecting results older than `max_stale_slots`.
+pub fn feed_value(feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+    let feed = load_feed(feed)?;
+    // A result newer than the clock usually means a local test validator was
+    // never advanced; report it separately from ordinary staleness.
+    if feed.result.slot > clock.slot {
+        msg!(
+            "Feed result slot {} is ahead of the current slot {}; is the test clock behind?",
+            feed.result.slot,
+            clock.slot
+        );
+        return Err(ErrorCode::FeedFromFutureSlot.into());
+    }
+    let value = feed
+        .get_value(clock, max_stale_slots, 1, false)
+        .map_err(|e| {
+            msg!("Get Value Error: {:?}", e);
+            ErrorCode::StaleFeed
+        })?;
+    Ok(value.mantissa())
+}
+
+/// Reject accounts that are not owned by the Switchboard On-Demand program.
+pub fn check_feed_owner(feed: &AccountInfo) -> Result<()> {
+    if feed.owner != &ON_DEMAND_MAINNET_PID && feed.owner != &ON_DEMAND_DEVNET_PID {
+        msg!("Feed {} is owned by {}", feed.key(), feed.owner);
+        return Err(ErrorCode::InvalidFeedOwner.into());
+    }
+    Ok(())
+}
+
+/// Parse a pull feed and return the slot its current result was produced at.
+pub fn feed_result_slot(feed: &AccountInfo) -> Result<u64> {
+    let feed = load_feed(feed)?;
+    Ok(feed.result.slot)
+}
+
+// Approximate slot duration, used to estimate when a feed result was produced.
+pub const MS_PER_SLOT: i64 = 400;
+
+#[program]
+pub mod sb_on_demand_monotonic {
+    use super::*;
+
+    // Record a feed value only if its result slot is not earlier than the last
+    // recorded one; re-reading the same result is allowed. The stored timestamp
+    // is an estimate that backs the clock's unix timestamp off by the result's
+    // age in slots, and is not used for the ordering check since its rounding
+    // can make the same result look older on a later read.
+    pub fn record_monotonic(ctx: Context<RecordMonotonic>, max_stale_slots: u64) -> Result<()> {
+        let clock = Clock::get()?;
+        let value = feed_value(&ctx.accounts.feed, &clock, max_stale_slots)?;
+        let result_slot = feed_result_slot(&ctx.accounts.feed)?;
+        let age_ms = (clock.slot.saturating_sub(result_slot) as i64).saturating_mul(MS_PER_SLOT);
+        let estimated_ts = clock.unix_timestamp.saturating_sub(age_ms / 1000);
+
+        let series = &mut ctx.accounts.series;
+        if series.feed == Pubkey::default() {
+            // First call: nothing to compare against yet.
+            series.feed = ctx.accounts.feed.key();
+            series.bump = ctx.bumps.series;
+        } else if result_slot < series.last_result_slot {
+            msg!("Result slot {} is before last recorded {}", result_slot, series.last_result_slot);
+            return Err(ErrorCode::TimestampRegression.into());
+        }
+        series.value = value;
+        series.last_result_slot = result_slot;
+        series.last_timestamp = estimated_ts;
+        msg!("price: {} at ~{}", value, estimated_ts);
+        Ok(())
+    }
+}
+
+// === Accounts ===
+#[account]
+pub struct MonotonicSeries {
+    pub feed: Pubkey,
+    pub value: i128,
+    pub last_timestamp: i64, // Estimated unix time of the last recorded result
+    pub last_result_slot: u64, // Slot the last recorded result was produced at
+    pub bump: u8,
+}
+
+// === Instructions ===
+#[derive(Accounts)]
+pub struct RecordMonotonic<'info> {
+    #[account(init_if_needed,
+        payer = payer,
+        seeds = [b"monotonicSeries".as_ref(), feed.key().as_ref()],
+        space = 8 + 32 + 16 + 8 + 8 + 1,
+        bump)]
+    pub series: Account<'info, MonotonicSeries>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// === Errors ===
+#[error_code]
+pub enum ErrorCode {
+    #[msg("The account is not a valid Switchboard pull feed.")]
+    InvalidFeedAccount,
+    #[msg("The feed value is stale.")]
+    StaleFeed,
+    #[msg("The feed account is not owned by the Switchboard program.")]
+    InvalidFeedOwner,
+    #[msg("The feed result is from a slot ahead of the current clock.")]
+    FeedFromFutureSlot,
+    #[msg("The feed result is older than the last recorded result.")]
+    TimestampRegression,
+}
@@ -0,0 +1,263 @@
+use anchor_lang::prelude::*;
+use std::cell::Ref;
+use anchor_spl::token::{self, Mint, Token, TokenAccount};
+use switchboard_on_demand::on_demand::accounts::pull_feed::PullFeedAccountData;
+use switchboard_on_demand::program_id::{ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
+
+declare_id!("4xcTFFdX5KAfzDUE5XnWKkDykxoPT3duua7hqSanBsN9");
+
+/// Parse a pull feed account, rejecting accounts not owned by Switchboard
+/// and accounts too short to hold one instead of letting the SDK panic on
+/// truncated data.
+pub fn load_feed<'a>(feed: &'a AccountInfo) -> Result<Ref<'a, PullFeedAccountData>> {
+    check_feed_owner(feed)?;
+    let feed_account = feed.data.borrow();
+    let min_len = 8 + std::mem::size_of::<PullFeedAccountData>();
+    if feed_account.len() < min_len {
+        msg!("Feed account holds {} bytes, expected at least {}", feed_account.len(), min_len);
+        return Err(ErrorCode::InvalidFeedAccount.into());
+    }
+    PullFeedAccountData::parse(feed_account).map_err(|e| {
+        msg!("Parse Error: {:?}", e);
+        ErrorCode::InvalidFeedAccount.into()
+    })
+}
+
+/// Parse a pull feed and return its median value as an 18-decimal fixed point
+/// integer, rejecting results older than `max_stale_slots`.
+pub fn feed_value(feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+    let feed = load_feed(feed)?;
+    // A result newer than the clock usually means a local test validator was
+    // never advanced; report it separately from ordinary staleness.
+    if feed.result.slot > clock.slot {
+        msg!(
+            "Feed result slot {} is ahead of the current slot {}; is the test clock behind?",
+            feed.result.slot,
+            clock.slot
+        );
+        return Err(ErrorCode::FeedFromFutureSlot.into());
+    }
+    let value = feed
+        .get_value(clock, max_stale_slots, 1, false)
+        .map_err(|e| {
+            msg!("Get Value Error: {:?}", e);
+            ErrorCode::StaleFeed
+        })?;
+    Ok(value.mantissa())
+}
+
+/// Reject accounts that are not owned by the Switchboard On-Demand program.
+pub fn check_feed_owner(feed: &AccountInfo) -> Result<()> {
+    if feed.owner != &ON_DEMAND_MAINNET_PID && feed.owner != &ON_DEMAND_DEVNET_PID {
+        msg!("Feed {} is owned by {}", feed.key(), feed.owner);
+        return Err(ErrorCode::InvalidFeedOwner.into());
+    }
+    Ok(())
+}
+
+/// Parse a pull feed and return the slot its current result was produced at.
+pub fn feed_result_slot(feed: &AccountInfo) -> Result<u64> {
+    let feed = load_feed(feed)?;
+    Ok(feed.result.slot)
+}
+
+// 10^18, the fixed point scale of feed values.
+pub const SCALE: i128 = 1_000_000_000_000_000_000;
+
+/// Reward for a move from `last_value` to `value`: `reward_per_bps` per basis
+/// point of movement, capped at `max_reward`. Zero when there is no prior value.
+pub fn movement_reward(last_value: i128, value: i128, reward_per_bps: u64, max_reward: u64) -> Result<u64> {
+    if last_value == 0 {
+        return Ok(0);
+    }
+    let move_bps = value
+        .checked_sub(last_value)
+        .and_then(|diff| diff.unsigned_abs().checked_mul(10_000))
+        .map(|diff| diff / last_value.unsigned_abs())
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(move_bps.saturating_mul(reward_per_bps as u128).min(max_reward as u128) as u64)
+}
+
+#[program]
+pub mod sb_on_demand_movement_rewards {
+    use super::*;
+
+    pub fn init_rewards(ctx: Context<InitRewards>, reward_per_bps: u64, max_reward: u64) -> Result<()> {
+        check_feed_owner(&ctx.accounts.feed)?;
+        let rewards = &mut ctx.accounts.rewards;
+        rewards.authority = ctx.accounts.authority.key();
+        rewards.feed = ctx.accounts.feed.key();
+        rewards.mint = ctx.accounts.mint.key();
+        rewards.reward_per_bps = reward_per_bps;
+        rewards.max_reward = max_reward;
+        rewards.bump = ctx.bumps.rewards;
+        Ok(())
+    }
+
+    // Make `claimant` eligible for rewards by creating their claim record.
+    // Only the rewards authority can register claimants.
+    pub fn register_claimant(ctx: Context<RegisterClaimant>, claimant: Pubkey) -> Result<()> {
+        let claim = &mut ctx.accounts.claim;
+        claim.rewards = ctx.accounts.rewards.key();
+        claim.claimant = claimant;
+        claim.bump = ctx.bumps.claim;
+        Ok(())
+    }
+
+    // Mint the claimant rewards proportional to the feed's move, in basis
+    // points, since the value recorded at their last claim, capped at
+    // `max_reward`. The first claim only records a value, and each feed
+    // result can be claimed once per claimant.
+    pub fn claim_movement_reward(ctx: Context<ClaimMovementReward>, max_stale_slots: u64) -> Result<()> {
+        let clock = Clock::get()?;
+        let value = feed_value(&ctx.accounts.feed, &clock, max_stale_slots)?;
+        let result_slot = feed_result_slot(&ctx.accounts.feed)?;
+        let rewards = &ctx.accounts.rewards;
+        let claim = &mut ctx.accounts.claim;
+        require!(result_slot > claim.last_slot, ErrorCode::DuplicateResult);
+
+        let reward = movement_reward(claim.last_value, value, rewards.reward_per_bps, rewards.max_reward)?;
+        claim.last_value = value;
+        claim.last_slot = result_slot;
+        claim.total_paid = claim.total_paid.checked_add(reward).ok_or(ErrorCode::MathOverflow)?;
+
+        if reward > 0 {
+            let mint_key = rewards.mint;
+            let seeds: &[&[u8]] = &[b"rewards".as_ref(), mint_key.as_ref(), &[rewards.bump]];
+            token::mint_to(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::MintTo {
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to: ctx.accounts.recipient_tokens.to_account_info(),
+                        authority: ctx.accounts.rewards.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                reward,
+            )?;
+        }
+        msg!("price: {}, reward: {}", value, reward);
+        Ok(())
+    }
+}
+
+// === Accounts ===
+#[account]
+pub struct MovementRewards {
+    pub authority: Pubkey, // Registers eligible claimants
+    pub feed: Pubkey,
+    pub mint: Pubkey,
+    pub reward_per_bps: u64, // Reward tokens per basis point of price movement
+    pub max_reward: u64,     // Cap on a single reward
+    pub bump: u8,
+}
+
+#[account]
+pub struct RewardClaim {
+    pub rewards: Pubkey,
+    pub claimant: Pubkey,
+    pub last_value: i128, // 0 until the first claim
+    pub last_slot: u64,   // Result slot of last_value
+    pub total_paid: u64,  // Rewards minted to this claimant so far
+    pub bump: u8,
+}
+
+// === Instructions ===
+#[derive(Accounts)]
+pub struct InitRewards<'info> {
+    #[account(init,
+        payer = authority,
+        seeds = [b"rewards".as_ref(), mint.key().as_ref()],
+        space = 8 + 32 + 32 + 32 + 8 + 8 + 1,
+        bump)]
+    pub rewards: Account<'info, MovementRewards>,
+    #[account(init,
+        payer = authority,
+        mint::decimals = 6,
+        mint::authority = rewards)]
+    pub mint: Account<'info, Mint>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(claimant: Pubkey)]
+pub struct RegisterClaimant<'info> {
+    #[account(has_one = authority)]
+    pub rewards: Account<'info, MovementRewards>,
+    #[account(init,
+        payer = authority,
+        seeds = [b"rewardClaim".as_ref(), rewards.key().as_ref(), claimant.as_ref()],
+        space = 8 + 32 + 32 + 16 + 8 + 8 + 1,
+        bump)]
+    pub claim: Account<'info, RewardClaim>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimMovementReward<'info> {
+    #[account(
+        seeds = [b"rewards".as_ref(), mint.key().as_ref()],
+        bump = rewards.bump,
+        has_one = mint,
+        has_one = feed)]
+    pub rewards: Account<'info, MovementRewards>,
+    #[account(mut,
+        seeds = [b"rewardClaim".as_ref(), rewards.key().as_ref(), claimant.key().as_ref()],
+        bump = claim.bump,
+        has_one = rewards,
+        has_one = claimant)]
+    pub claim: Account<'info, RewardClaim>,
+    pub claimant: Signer<'info>,
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    #[account(mut, token::mint = mint, token::authority = claimant)]
+    pub recipient_tokens: Account<'info, TokenAccount>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+// === Errors ===
+#[error_code]
+pub enum ErrorCode {
+    #[msg("The account is not a valid Switchboard pull feed.")]
+    InvalidFeedAccount,
+    #[msg("The feed value is stale.")]
+    StaleFeed,
+    #[msg("The feed account is not owned by the Switchboard program.")]
+    InvalidFeedOwner,
+    #[msg("Arithmetic overflow.")]
+    MathOverflow,
+    #[msg("The feed result is from a slot ahead of the current clock.")]
+    FeedFromFutureSlot,
+    #[msg("This feed result has already been recorded.")]
+    DuplicateResult,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(whole: i128) -> i128 {
+        whole * SCALE
+    }
+
+    #[test]
+    fn movement_reward_scales_with_move_and_caps() {
+        // First claim: no prior value, no reward.
+        assert_eq!(movement_reward(0, price(100), 10, 1_000).unwrap(), 0);
+        // 100 -> 101 is 100 bps in either direction.
+        assert_eq!(movement_reward(price(100), price(101), 10, 1_000_000).unwrap(), 1_000);
+        assert_eq!(movement_reward(price(100), price(99), 10, 1_000_000).unwrap(), 1_000);
+        // 100 -> 150 is 5000 bps, capped at max_reward.
+        assert_eq!(movement_reward(price(100), price(150), 10, 20_000).unwrap(), 20_000);
+    }
+}
@@ -0,0 +1,435 @@
+use anchor_lang::prelude::*;
+use std::cell::Ref;
+use switchboard_on_demand::on_demand::accounts::pull_feed::PullFeedAccountData;
+use switchboard_on_demand::program_id::{ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
+
+declare_id!("Hn3CSDTqTi3e4yiBpLzpm4nfu4f2Cdwhz3k3qiskVkA6");
+
+/// Parse a pull feed account, rejecting accounts not owned by Switchboard
+/// and accounts too short to hold one instead of letting the SDK panic on
+/// truncated data.
+pub fn load_feed<'a>(feed: &'a AccountInfo) -> Result<Ref<'a, PullFeedAccountData>> {
+    check_feed_owner(feed)?;
+    let feed_account = feed.data.borrow();
+    let min_len = 8 + std::mem::size_of::<PullFeedAccountData>();
+    if feed_account.len() < min_len {
+        msg!("Feed account holds {} bytes, expected at least {}", feed_account.len(), min_len);
+        return Err(ErrorCode::InvalidFeedAccount.into());
+    }
+    PullFeedAccountData::parse(feed_account).map_err(|e| {
+        msg!("Parse Error: {:?}", e);
+        ErrorCode::InvalidFeedAccount.into()
+    })
+}
+
+/// Parse a pull feed and return its median value as an 18-decimal fixed point
+/// integer, rejecting results older than `max_stale_slots`.
+pub fn feed_value(feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+    let feed = load_feed(feed)?;
+    // A result newer than the clock usually means a local test validator was
+    // never advanced; report it separately from ordinary staleness.
+    if feed.result.slot > clock.slot {
+        msg!(
+            "Feed result slot {} is ahead of the current slot {}; is the test clock behind?",
+            feed.result.slot,
+            clock.slot
+        );
+        return Err(ErrorCode::FeedFromFutureSlot.into());
+    }
+    let value = feed
+        .get_value(clock, max_stale_slots, 1, false)
+        .map_err(|e| {
+            msg!("Get Value Error: {:?}", e);
+            ErrorCode::StaleFeed
+        })?;
+    Ok(value.mantissa())
+}
+
+/// Reject accounts that are not owned by the Switchboard On-Demand program.
+pub fn check_feed_owner(feed: &AccountInfo) -> Result<()> {
+    if feed.owner != &ON_DEMAND_MAINNET_PID && feed.owner != &ON_DEMAND_DEVNET_PID {
+        msg!("Feed {} is owned by {}", feed.key(), feed.owner);
+        return Err(ErrorCode::InvalidFeedOwner.into());
+    }
+    Ok(())
+}
+
+/// Parse a pull feed and return the slot its current result was produced at.
+pub fn feed_result_slot(feed: &AccountInfo) -> Result<u64> {
+    let feed = load_feed(feed)?;
+    Ok(feed.result.slot)
+}
+
+// 10^18, the fixed point scale of feed values.
+pub const SCALE: i128 = 1_000_000_000_000_000_000;
+
+#[program]
+pub mod sb_on_demand_parimutuel {
+    use super::*;
+
+    // Open a SOL parimutuel pool on whether the feed will be below (outcome 0)
+    // or at/above (outcome 1) `threshold` once `resolve_slot` is reached.
+    pub fn create_pool(
+        ctx: Context<CreatePool>,
+        id: u64,
+        threshold: i128,
+        resolve_slot: u64,
+        rake_bps: u16,
+    ) -> Result<()> {
+        require!(rake_bps <= 10_000, ErrorCode::InvalidRake);
+        check_feed_owner(&ctx.accounts.feed)?;
+        let pool = &mut ctx.accounts.pool;
+        pool.authority = ctx.accounts.authority.key();
+        pool.feed = ctx.accounts.feed.key();
+        pool.id = id;
+        pool.threshold = threshold;
+        pool.resolve_slot = resolve_slot;
+        pool.rake_bps = rake_bps;
+        pool.bump = ctx.bumps.pool;
+        Ok(())
+    }
+
+    pub fn place_bet(ctx: Context<PlaceBet>, outcome: u8, amount: u64) -> Result<()> {
+        require!(outcome < 2, ErrorCode::InvalidOutcome);
+        let pool = &mut ctx.accounts.pool;
+        require!(!pool.resolved, ErrorCode::PoolAlreadyResolved);
+        require!(Clock::get()?.slot < pool.resolve_slot, ErrorCode::PoolAlreadyResolved);
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.user.to_account_info(),
+                    to: pool.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+        pool.totals[outcome as usize] = pool.totals[outcome as usize]
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let bet = &mut ctx.accounts.bet;
+        if bet.amount == 0 {
+            bet.user = ctx.accounts.user.key();
+            bet.pool = pool.key();
+            bet.outcome = outcome;
+            bet.bump = ctx.bumps.bet;
+        }
+        require!(bet.outcome == outcome, ErrorCode::InvalidOutcome);
+        bet.amount = bet.amount.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    // Resolve the pool from a feed result produced at or after `resolve_slot`
+    // and pay the rake to the authority. With no winning bets, the pool is
+    // refunded in full instead.
+    pub fn resolve_pool(ctx: Context<ResolvePool>, max_stale_slots: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let rake = pool.resolve(&ctx.accounts.feed, &Clock::get()?, max_stale_slots)?;
+        if rake > 0 {
+            pool.sub_lamports(rake)?;
+            ctx.accounts.authority.add_lamports(rake)?;
+        }
+        msg!("Resolved: outcome {}, refund: {}", pool.winning_outcome, pool.refund);
+        Ok(())
+    }
+
+    // Pay out a bet's share of the pool, or its stake if the pool was refunded.
+    pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
+        let pool = &ctx.accounts.pool;
+        require!(pool.resolved, ErrorCode::PoolNotResolvable);
+        let bet = &mut ctx.accounts.bet;
+        require!(!bet.claimed, ErrorCode::AlreadyClaimed);
+        let payout = pool.payout(bet);
+        bet.claimed = true;
+        if payout > 0 {
+            ctx.accounts.pool.sub_lamports(payout)?;
+            ctx.accounts.user.add_lamports(payout)?;
+        }
+        msg!("Paid out {} lamports", payout);
+        Ok(())
+    }
+}
+
+// === Accounts ===
+#[account]
+pub struct Pool {
+    pub authority: Pubkey, // Receives the rake
+    pub feed: Pubkey,
+    pub id: u64,
+    pub threshold: i128,     // Outcome 1 wins if the price is at or above this
+    pub resolve_slot: u64,   // Betting closes and resolution opens at this slot
+    pub rake_bps: u16,
+    pub totals: [u64; 2],    // Lamports staked on each outcome
+    pub resolved: bool,
+    pub winning_outcome: u8,
+    pub refund: bool,        // Nobody bet on the winning outcome
+    pub distributable: u64,  // Lamports shared by the winners after the rake
+    pub bump: u8,
+}
+
+impl Pool {
+    // Resolve from a feed result produced at or after `resolve_slot`, returning
+    // the rake owed to the authority. With no winning bets nothing is raked and
+    // every bet is refunded.
+    pub fn resolve(&mut self, feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<u64> {
+        require!(!self.resolved, ErrorCode::PoolAlreadyResolved);
+        let result_slot = feed_result_slot(feed)?;
+        if clock.slot < self.resolve_slot || result_slot < self.resolve_slot {
+            msg!("Pool resolves at slot {}, feed result is from {}", self.resolve_slot, result_slot);
+            return Err(ErrorCode::PoolNotResolvable.into());
+        }
+        let price = feed_value(feed, clock, max_stale_slots)?;
+        let winning_outcome = (price >= self.threshold) as u8;
+        let total = self.totals[0].checked_add(self.totals[1]).ok_or(ErrorCode::MathOverflow)?;
+
+        self.resolved = true;
+        self.winning_outcome = winning_outcome;
+        self.refund = self.totals[winning_outcome as usize] == 0;
+        let rake = if self.refund {
+            0
+        } else {
+            (total as u128 * self.rake_bps as u128 / 10_000) as u64
+        };
+        self.distributable = total - rake;
+        Ok(rake)
+    }
+
+    // A bet's share of a resolved pool, or its stake if the pool was refunded.
+    pub fn payout(&self, bet: &Bet) -> u64 {
+        if self.refund {
+            bet.amount
+        } else if bet.outcome == self.winning_outcome {
+            let winning_total = self.totals[self.winning_outcome as usize];
+            (bet.amount as u128 * self.distributable as u128 / winning_total as u128) as u64
+        } else {
+            0
+        }
+    }
+}
+
+#[account]
+pub struct Bet {
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    pub outcome: u8,
+    pub amount: u64,
+    pub claimed: bool,
+    pub bump: u8,
+}
+
+// === Instructions ===
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct CreatePool<'info> {
+    #[account(init,
+        payer = authority,
+        seeds = [b"pool".as_ref(), authority.key().as_ref(), id.to_le_bytes().as_ref()],
+        space = 8 + 32 + 32 + 8 + 16 + 8 + 2 + 8 * 2 + 1 + 1 + 1 + 8 + 1,
+        bump)]
+    pub pool: Account<'info, Pool>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceBet<'info> {
+    #[account(mut,
+        seeds = [b"pool".as_ref(), pool.authority.as_ref(), pool.id.to_le_bytes().as_ref()],
+        bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(init_if_needed,
+        payer = user,
+        seeds = [b"bet".as_ref(), pool.key().as_ref(), user.key().as_ref()],
+        space = 8 + 32 + 32 + 1 + 8 + 1 + 1,
+        bump)]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResolvePool<'info> {
+    #[account(mut,
+        seeds = [b"pool".as_ref(), pool.authority.as_ref(), pool.id.to_le_bytes().as_ref()],
+        bump = pool.bump,
+        has_one = feed,
+        has_one = authority)]
+    pub pool: Account<'info, Pool>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    /// CHECK: only receives the rake; checked against the pool
+    #[account(mut)]
+    pub authority: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWinnings<'info> {
+    #[account(mut,
+        seeds = [b"pool".as_ref(), pool.authority.as_ref(), pool.id.to_le_bytes().as_ref()],
+        bump = pool.bump)]
+    pub pool: Account<'info, Pool>,
+    #[account(mut,
+        seeds = [b"bet".as_ref(), pool.key().as_ref(), user.key().as_ref()],
+        bump = bet.bump,
+        has_one = user,
+        has_one = pool)]
+    pub bet: Account<'info, Bet>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+// === Errors ===
+#[error_code]
+pub enum ErrorCode {
+    #[msg("The account is not a valid Switchboard pull feed.")]
+    InvalidFeedAccount,
+    #[msg("The feed value is stale.")]
+    StaleFeed,
+    #[msg("The feed account is not owned by the Switchboard program.")]
+    InvalidFeedOwner,
+    #[msg("Arithmetic overflow.")]
+    MathOverflow,
+    #[msg("The feed result is from a slot ahead of the current clock.")]
+    FeedFromFutureSlot,
+    #[msg("The outcome must be 0 (below) or 1 (at or above the threshold).")]
+    InvalidOutcome,
+    #[msg("The pool has already been resolved or betting has closed.")]
+    PoolAlreadyResolved,
+    #[msg("The pool cannot be resolved until its resolve slot has a feed result.")]
+    PoolNotResolvable,
+    #[msg("The bet has already been claimed.")]
+    AlreadyClaimed,
+    #[msg("The rake must be at most 10000 basis points.")]
+    InvalidRake,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(whole: i128) -> i128 {
+        whole * SCALE
+    }
+
+    // Slot of the test clock; fixture results are produced a few slots before it.
+    const NOW: u64 = 1_000;
+
+    fn clock_at(slot: u64) -> Clock {
+        Clock { slot, ..Clock::default() }
+    }
+
+    /// Backing storage for a Switchboard pull feed account whose only oracle
+    /// reported `value` at `slot`, owned by the on-demand program.
+    struct TestFeed {
+        key: Pubkey,
+        lamports: u64,
+        buf: Vec<u8>,
+        start: usize,
+    }
+
+    impl TestFeed {
+        fn new(value: i128, slot: u64) -> Self {
+            Self::with(value, slot, |_| {})
+        }
+
+        /// Like `new`, letting `edit` adjust the account before it is written.
+        fn with(value: i128, slot: u64, edit: impl FnOnce(&mut PullFeedAccountData)) -> Self {
+            // SAFETY: the account is a zero-copy plain-old-data struct, for
+            // which all zero bytes are a valid value.
+            let mut feed: PullFeedAccountData = unsafe { std::mem::zeroed() };
+            feed.result.value = value;
+            feed.result.slot = slot;
+            feed.submissions[0].oracle = Pubkey::new_unique();
+            feed.submissions[0].slot = slot;
+            feed.submissions[0].value = value;
+            edit(&mut feed);
+            // SAFETY: as above, the struct has no padding-sensitive invariants.
+            let bytes = unsafe {
+                std::slice::from_raw_parts(
+                    &feed as *const PullFeedAccountData as *const u8,
+                    std::mem::size_of::<PullFeedAccountData>(),
+                )
+            };
+            // The SDK casts the bytes after the discriminator in place, so they
+            // must be 16-byte aligned for the i128 fields.
+            let mut buf = vec![0u8; 8 + bytes.len() + 16];
+            let start = (16 - (buf.as_ptr() as usize + 8) % 16) % 16;
+            let discriminator = anchor_lang::solana_program::hash::hash(b"account:PullFeedAccountData");
+            buf[start..start + 8].copy_from_slice(&discriminator.to_bytes()[..8]);
+            buf[start + 8..start + 8 + bytes.len()].copy_from_slice(bytes);
+            Self { key: Pubkey::new_unique(), lamports: 1, buf, start }
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            let len = 8 + std::mem::size_of::<PullFeedAccountData>();
+            let data = &mut self.buf[self.start..self.start + len];
+            AccountInfo::new(&self.key, false, false, &mut self.lamports, data, &ON_DEMAND_MAINNET_PID, false, 0)
+        }
+    }
+
+    fn open_pool(totals: [u64; 2]) -> Pool {
+        Pool {
+            authority: Pubkey::new_unique(),
+            feed: Pubkey::new_unique(),
+            id: 1,
+            threshold: price(100),
+            resolve_slot: NOW - 5,
+            rake_bps: 100,
+            totals,
+            resolved: false,
+            winning_outcome: 0,
+            refund: false,
+            distributable: 0,
+            bump: 255,
+        }
+    }
+
+    fn bet(outcome: u8, amount: u64) -> Bet {
+        Bet {
+            user: Pubkey::new_unique(),
+            pool: Pubkey::new_unique(),
+            outcome,
+            amount,
+            claimed: false,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn pool_pays_winners_pro_rata_after_the_rake() {
+        let mut pool = open_pool([300, 700]);
+        // Not resolvable from a result produced before resolve_slot.
+        let mut early = TestFeed::new(price(120), NOW - 6);
+        assert_eq!(
+            pool.resolve(&early.info(), &clock_at(NOW), 10).unwrap_err(),
+            Error::from(ErrorCode::PoolNotResolvable)
+        );
+        assert!(!pool.resolved);
+
+        let mut feed = TestFeed::new(price(120), NOW - 5);
+        assert_eq!(pool.resolve(&feed.info(), &clock_at(NOW), 10).unwrap(), 10);
+        assert_eq!((pool.winning_outcome, pool.refund, pool.distributable), (1, false, 990));
+        // 350 of the 700 on the winning side takes half of the 990.
+        assert_eq!(pool.payout(&bet(1, 350)), 495);
+        assert_eq!(pool.payout(&bet(0, 300)), 0);
+        assert_eq!(
+            pool.resolve(&feed.info(), &clock_at(NOW), 10).unwrap_err(),
+            Error::from(ErrorCode::PoolAlreadyResolved)
+        );
+    }
+
+    #[test]
+    fn pool_without_winners_refunds_every_bet() {
+        let mut pool = open_pool([500, 0]);
+        let mut feed = TestFeed::new(price(120), NOW);
+        assert_eq!(pool.resolve(&feed.info(), &clock_at(NOW), 10).unwrap(), 0);
+        assert_eq!((pool.winning_outcome, pool.refund, pool.distributable), (1, true, 500));
+        assert_eq!(pool.payout(&bet(0, 200)), 200);
+        assert_eq!(pool.payout(&bet(0, 300)), 300);
+    }
+}
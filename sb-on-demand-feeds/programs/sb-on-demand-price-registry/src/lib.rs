@@ -0,0 +1,262 @@
+use anchor_lang::prelude::*;
+use std::cell::Ref;
+use switchboard_on_demand::on_demand::accounts::pull_feed::PullFeedAccountData;
+use switchboard_on_demand::program_id::{ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
+
+declare_id!("FUHfPEshaHkRkufsHnNeygtTHfxvfE4virkTPDSpuCCd");
+
+/// Parse a pull feed account, rejecting accounts not owned by Switchboard
+/// and accounts too short to hold one instead of letting the SDK panic on
+/// truncated data.
+pub fn load_feed<'a>(feed: &'a AccountInfo) -> Result<Ref<'a, PullFeedAccountData>> {
+    check_feed_owner(feed)?;
+    let feed_account = feed.data.borrow();
+    let min_len = 8 + std::mem::size_of::<PullFeedAccountData>();
+    if feed_account.len() < min_len {
+        msg!("Feed account holds {} bytes, expected at least {}", feed_account.len(), min_len);
+        return Err(ErrorCode::InvalidFeedAccount.into());
+    }
+    PullFeedAccountData::parse(feed_account).map_err(|e| {
+        msg!("Parse Error: {:?}", e);
+        ErrorCode::InvalidFeedAccount.into()
+    })
+}
+
+/// Parse a pull feed and return its median value as an 18-decimal fixed point
+/// integer, rejecting results older than `max_stale_slots`.
+pub fn feed_value(feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+    let feed = load_feed(feed)?;
+    // A result newer than the clock usually means a local test validator was
+    // never advanced; report it separately from ordinary staleness.
+    if feed.result.slot > clock.slot {
+        msg!(
+            "Feed result slot {} is ahead of the current slot {}; is the test clock behind?",
+            feed.result.slot,
+            clock.slot
+        );
+        return Err(ErrorCode::FeedFromFutureSlot.into());
+    }
+    let value = feed
+        .get_value(clock, max_stale_slots, 1, false)
+        .map_err(|e| {
+            msg!("Get Value Error: {:?}", e);
+            ErrorCode::StaleFeed
+        })?;
+    Ok(value.mantissa())
+}
+
+/// Reject accounts that are not owned by the Switchboard On-Demand program.
+pub fn check_feed_owner(feed: &AccountInfo) -> Result<()> {
+    if feed.owner != &ON_DEMAND_MAINNET_PID && feed.owner != &ON_DEMAND_DEVNET_PID {
+        msg!("Feed {} is owned by {}", feed.key(), feed.owner);
+        return Err(ErrorCode::InvalidFeedOwner.into());
+    }
+    Ok(())
+}
+
+/// Parse a pull feed and return the slot its current result was produced at.
+pub fn feed_result_slot(feed: &AccountInfo) -> Result<u64> {
+    let feed = load_feed(feed)?;
+    Ok(feed.result.slot)
+}
+
+// 10^18, the fixed point scale of feed values.
+pub const SCALE: i128 = 1_000_000_000_000_000_000;
+
+#[program]
+pub mod sb_on_demand_price_registry {
+    use super::*;
+
+    // Permissionlessly publish the current feed value to its registry entry so
+    // other programs can read it from a fixed account layout without
+    // depending on the Switchboard SDK.
+    pub fn publish_price(ctx: Context<PublishPrice>, max_stale_slots: u64) -> Result<()> {
+        let clock = Clock::get()?;
+        let value = feed_value(&ctx.accounts.feed, &clock, max_stale_slots)?;
+        let result_slot = feed_result_slot(&ctx.accounts.feed)?;
+        let entry = &mut ctx.accounts.registry_entry;
+        if entry.feed == Pubkey::default() {
+            entry.feed = ctx.accounts.feed.key();
+            entry.bump = ctx.bumps.registry_entry;
+        }
+        entry.publish(value, result_slot, clock.slot)?;
+        msg!("Published {} for {} at slot {}", value, entry.feed, clock.slot);
+        Ok(())
+    }
+
+    // Example consumer: read a registry entry, rejecting it if its underlying
+    // result is older than `max_stale_slots`.
+    pub fn read_registry(ctx: Context<ReadRegistry>, max_stale_slots: u64) -> Result<i128> {
+        let clock = Clock::get()?;
+        let entry = &ctx.accounts.registry_entry;
+        let staleness = clock.slot.saturating_sub(entry.result_slot);
+        if staleness > max_stale_slots {
+            msg!("Registry entry is {} slots old", staleness);
+            return Err(ErrorCode::StaleFeed.into());
+        }
+        msg!("price: {} ({} slots old)", entry.value, staleness);
+        Ok(entry.value)
+    }
+
+    // Let a keeper check whether publishing the feed would improve its
+    // registry entry before paying for the write.
+    pub fn compare_to_registry(ctx: Context<CompareToRegistry>) -> Result<UpdateComparison> {
+        let result_slot = feed_result_slot(&ctx.accounts.feed)?;
+        let entry = &ctx.accounts.registry_entry;
+        msg!("feed slot {}, cached slot {}", result_slot, entry.result_slot);
+        Ok(entry.compare(result_slot))
+    }
+}
+
+// === Accounts ===
+// Fixed layout so consumers can read entries without Anchor:
+//   [0..8] discriminator, [8..40] feed, [40..56] value (i128 LE),
+//   [56..64] result_slot (u64 LE), [64..72] published_slot (u64 LE), [72] bump
+#[account]
+pub struct RegistryEntry {
+    pub feed: Pubkey,
+    pub value: i128,         // 18 decimals
+    pub result_slot: u64,    // Slot the oracle result was produced at
+    pub published_slot: u64, // Slot the entry was last written
+    pub bump: u8,
+}
+
+impl RegistryEntry {
+    /// Overwrite the entry with `value`, produced at `result_slot`. A result
+    /// already published is rejected, so each publish carries a new result.
+    pub fn publish(&mut self, value: i128, result_slot: u64, published_slot: u64) -> Result<()> {
+        require!(result_slot > self.result_slot, ErrorCode::DuplicateResult);
+        self.value = value;
+        self.result_slot = result_slot;
+        self.published_slot = published_slot;
+        Ok(())
+    }
+
+    /// Whether a result from `result_slot` would be newer than the cached one.
+    pub fn compare(&self, result_slot: u64) -> UpdateComparison {
+        UpdateComparison {
+            is_newer: result_slot > self.result_slot,
+            slot_delta: result_slot as i64 - self.result_slot as i64,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct UpdateComparison {
+    pub is_newer: bool,
+    pub slot_delta: i64, // Feed result slot minus cached slot
+}
+
+// === Instructions ===
+#[derive(Accounts)]
+pub struct PublishPrice<'info> {
+    #[account(init_if_needed,
+        payer = payer,
+        seeds = [b"registry".as_ref(), feed.key().as_ref()],
+        space = 8 + 32 + 16 + 8 + 8 + 1,
+        bump)]
+    pub registry_entry: Account<'info, RegistryEntry>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReadRegistry<'info> {
+    #[account(has_one = feed)]
+    pub registry_entry: Account<'info, RegistryEntry>,
+    /// CHECK: only compared against the entry's feed
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CompareToRegistry<'info> {
+    #[account(seeds = [b"registry".as_ref(), feed.key().as_ref()], bump = registry_entry.bump)]
+    pub registry_entry: Account<'info, RegistryEntry>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+// === Errors ===
+#[error_code]
+pub enum ErrorCode {
+    #[msg("The account is not a valid Switchboard pull feed.")]
+    InvalidFeedAccount,
+    #[msg("The feed value is stale.")]
+    StaleFeed,
+    #[msg("The feed account is not owned by the Switchboard program.")]
+    InvalidFeedOwner,
+    #[msg("The feed result is from a slot ahead of the current clock.")]
+    FeedFromFutureSlot,
+    #[msg("This feed result has already been recorded.")]
+    DuplicateResult,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(whole: i128) -> i128 {
+        whole * SCALE
+    }
+
+    // Slot of the test clock.
+    const NOW: u64 = 1_000;
+
+    fn registry_entry(feed: Pubkey) -> RegistryEntry {
+        RegistryEntry { feed, value: 0, result_slot: 0, published_slot: 0, bump: 0 }
+    }
+
+    #[test]
+    fn registry_entry_rejects_republishing_a_result() {
+        let mut entry = registry_entry(Pubkey::new_unique());
+        entry.publish(price(10), 100, 101).unwrap();
+        // The same result, even in a later slot, is a duplicate...
+        assert_eq!(entry.publish(price(10), 100, 102).unwrap_err(), Error::from(ErrorCode::DuplicateResult));
+        assert_eq!(entry.publish(price(9), 99, 102).unwrap_err(), Error::from(ErrorCode::DuplicateResult));
+        assert_eq!((entry.value, entry.published_slot), (price(10), 101));
+        // ...while a newer one replaces it.
+        entry.publish(price(11), 101, 102).unwrap();
+        assert_eq!((entry.value, entry.result_slot), (price(11), 101));
+    }
+
+    #[test]
+    fn read_registry_requires_the_entry_feed() {
+        let (entry_key, feed, other_feed) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let feed_owner = Pubkey::new_unique();
+        let mut entry_data = Vec::new();
+        registry_entry(feed).try_serialize(&mut entry_data).unwrap();
+        let (mut entry_lamports, mut feed_lamports) = (1u64, 1u64);
+        let mut feed_data = vec![];
+        let infos = [
+            AccountInfo::new(&entry_key, false, false, &mut entry_lamports, &mut entry_data, &crate::ID, false, 0),
+            AccountInfo::new(&other_feed, false, false, &mut feed_lamports, &mut feed_data, &feed_owner, false, 0),
+        ];
+        let accounts = ReadRegistry::try_accounts(
+            &crate::ID,
+            &mut &infos[..],
+            &[],
+            &mut Default::default(),
+            &mut std::collections::BTreeSet::new(),
+        );
+        assert_eq!(
+            accounts.err().unwrap(),
+            Error::from(anchor_lang::error::ErrorCode::ConstraintHasOne)
+        );
+    }
+
+    #[test]
+    fn compare_reports_newer_and_older_results() {
+        let mut entry = registry_entry(Pubkey::new_unique());
+        entry.publish(price(1), NOW - 10, NOW - 9).unwrap();
+        let newer = entry.compare(NOW - 4);
+        assert_eq!((newer.is_newer, newer.slot_delta), (true, 6));
+        let older = entry.compare(NOW - 12);
+        assert_eq!((older.is_newer, older.slot_delta), (false, -2));
+        // The cached result itself is not worth republishing.
+        let same = entry.compare(NOW - 10);
+        assert_eq!((same.is_newer, same.slot_delta), (false, 0));
+    }
+}
@@ -0,0 +1,167 @@
+use anchor_lang::prelude::*;
+use std::cell::Ref;
+use switchboard_on_demand::on_demand::accounts::pull_feed::PullFeedAccountData;
+use switchboard_on_demand::program_id::{ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
+
+declare_id!("9SeKjsKAZdryuSLdVv6Kstbqduq6p8jLk8AJssp9MVmA");
+
+/// Parse a pull feed account, rejecting accounts not owned by Switchboard
+/// and accounts too short to hold one instead of letting the SDK panic on
+/// truncated data.
+pub fn load_feed<'a>(feed: &'a AccountInfo) -> Result<Ref<'a, PullFeedAccountData>> {
+    check_feed_owner(feed)?;
+    let feed_account = feed.data.borrow();
+    let min_len = 8 + std::mem::size_of::<PullFeedAccountData>();
+    if feed_account.len() < min_len {
+        msg!("Feed account holds {} bytes, expected at least {}", feed_account.len(), min_len);
+        return Err(ErrorCode::InvalidFeedAccount.into());
+    }
+    PullFeedAccountData::parse(feed_account).map_err(|e| {
+        msg!("Parse Error: {:?}", e);
+        ErrorCode::InvalidFeedAccount.into()
+    })
+}
+
+/// Parse a pull feed and return its median value as an 18-decimal fixed point
+/// integer, rejecting results older than `max_stale_slots`.
+pub fn feed_value(feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+    let feed = load_feed(feed)?;
+    // A result newer than the clock usually means a local test validator was
+    // never advanced; report it separately from ordinary staleness.
+    if feed.result.slot > clock.slot {
+        msg!(
+            "Feed result slot {} is ahead of the current slot {}; is the test clock behind?",
+            feed.result.slot,
+            clock.slot
+        );
+        return Err(ErrorCode::FeedFromFutureSlot.into());
+    }
+    let value = feed
+        .get_value(clock, max_stale_slots, 1, false)
+        .map_err(|e| {
+            msg!("Get Value Error: {:?}", e);
+            ErrorCode::StaleFeed
+        })?;
+    Ok(value.mantissa())
+}
+
+/// Reject accounts that are not owned by the Switchboard On-Demand program.
+pub fn check_feed_owner(feed: &AccountInfo) -> Result<()> {
+    if feed.owner != &ON_DEMAND_MAINNET_PID && feed.owner != &ON_DEMAND_DEVNET_PID {
+        msg!("Feed {} is owned by {}", feed.key(), feed.owner);
+        return Err(ErrorCode::InvalidFeedOwner.into());
+    }
+    Ok(())
+}
+
+#[program]
+pub mod sb_on_demand_proposals {
+    use super::*;
+
+    // Record a proposal that may only be executed once `feed` crosses `threshold`.
+    pub fn create_proposal(
+        ctx: Context<CreateProposal>,
+        id: u64,
+        threshold: i128,
+        execute_above: bool,
+    ) -> Result<()> {
+        // Pin only a genuine Switchboard feed; execute_proposal reads it
+        // through feed_value, which checks the owner again.
+        check_feed_owner(&ctx.accounts.feed)?;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.authority = ctx.accounts.authority.key();
+        proposal.feed = ctx.accounts.feed.key();
+        proposal.id = id;
+        proposal.threshold = threshold;
+        proposal.execute_above = execute_above;
+        proposal.executed = false;
+        proposal.bump = ctx.bumps.proposal;
+        Ok(())
+    }
+
+    // Execute a proposal once the verified feed value satisfies its condition.
+    pub fn execute_proposal(ctx: Context<ExecuteProposal>, max_stale_slots: u64) -> Result<()> {
+        let clock = Clock::get()?;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(!proposal.executed, ErrorCode::ProposalAlreadyExecuted);
+
+        let price = feed_value(&ctx.accounts.feed, &clock, max_stale_slots)?;
+        let condition_met = if proposal.execute_above {
+            price >= proposal.threshold
+        } else {
+            price <= proposal.threshold
+        };
+        if !condition_met {
+            msg!("price: {}, threshold: {}", price, proposal.threshold);
+            return Err(ErrorCode::ProposalConditionNotMet.into());
+        }
+
+        // The proposal's effect goes here; this example only records it.
+        proposal.executed = true;
+        proposal.executed_price = price;
+        proposal.executed_slot = clock.slot;
+        msg!("Proposal {} executed at price {}", proposal.id, price);
+        Ok(())
+    }
+}
+
+// === Accounts ===
+#[account]
+pub struct Proposal {
+    pub authority: Pubkey,
+    pub feed: Pubkey,
+    pub id: u64,
+    pub threshold: i128,     // 18-decimal value the feed must cross
+    pub execute_above: bool, // true: execute when price >= threshold
+    pub executed: bool,
+    pub executed_price: i128,
+    pub executed_slot: u64,
+    pub bump: u8,
+}
+
+// === Instructions ===
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct CreateProposal<'info> {
+    #[account(init,
+        payer = authority,
+        seeds = [b"proposal".as_ref(), authority.key().as_ref(), id.to_le_bytes().as_ref()],
+        space = 8 + 32 + 32 + 8 + 16 + 1 + 1 + 16 + 8 + 1,
+        bump)]
+    pub proposal: Account<'info, Proposal>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteProposal<'info> {
+    #[account(mut,
+        seeds = [b"proposal".as_ref(), authority.key().as_ref(), proposal.id.to_le_bytes().as_ref()],
+        bump = proposal.bump,
+        has_one = authority,
+        has_one = feed)]
+    pub proposal: Account<'info, Proposal>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+}
+
+// === Errors ===
+#[error_code]
+pub enum ErrorCode {
+    #[msg("The account is not a valid Switchboard pull feed.")]
+    InvalidFeedAccount,
+    #[msg("The feed value is stale.")]
+    StaleFeed,
+    #[msg("The proposal has already been executed.")]
+    ProposalAlreadyExecuted,
+    #[msg("The feed value does not yet satisfy the proposal condition.")]
+    ProposalConditionNotMet,
+    #[msg("The feed account is not owned by the Switchboard program.")]
+    InvalidFeedOwner,
+    #[msg("The feed result is from a slot ahead of the current clock.")]
+    FeedFromFutureSlot,
+}
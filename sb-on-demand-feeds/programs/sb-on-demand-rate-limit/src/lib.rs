@@ -0,0 +1,180 @@
+use anchor_lang::prelude::*;
+use std::cell::Ref;
+use switchboard_on_demand::on_demand::accounts::pull_feed::PullFeedAccountData;
+use switchboard_on_demand::program_id::{ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
+
+declare_id!("74qnpq9Vfs6EwmVEVuJ6EVFRHfmjbMY2zDWdzRGcf6uu");
+
+/// Parse a pull feed account, rejecting accounts not owned by Switchboard
+/// and accounts too short to hold one instead of letting the SDK panic on
+/// truncated data.
+pub fn load_feed<'a>(feed: &'a AccountInfo) -> Result<Ref<'a, PullFeedAccountData>> {
+    check_feed_owner(feed)?;
+    let feed_account = feed.data.borrow();
+    let min_len = 8 + std::mem::size_of::<PullFeedAccountData>();
+    if feed_account.len() < min_len {
+        msg!("Feed account holds {} bytes, expected at least {}", feed_account.len(), min_len);
+        return Err(ErrorCode::InvalidFeedAccount.into());
+    }
+    PullFeedAccountData::parse(feed_account).map_err(|e| {
+        msg!("Parse Error: {:?}", e);
+        ErrorCode::InvalidFeedAccount.into()
+    })
+}
+
+/// Parse a pull feed and return its median value as an 18-decimal fixed point
+/// integer, rejecting results older than `max_stale_slots`.
+pub fn feed_value(feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+    let feed = load_feed(feed)?;
+    // A result newer than the clock usually means a local test validator was
+    // never advanced; report it separately from ordinary staleness.
+    if feed.result.slot > clock.slot {
+        msg!(
+            "Feed result slot {} is ahead of the current slot {}; is the test clock behind?",
+            feed.result.slot,
+            clock.slot
+        );
+        return Err(ErrorCode::FeedFromFutureSlot.into());
+    }
+    let value = feed
+        .get_value(clock, max_stale_slots, 1, false)
+        .map_err(|e| {
+            msg!("Get Value Error: {:?}", e);
+            ErrorCode::StaleFeed
+        })?;
+    Ok(value.mantissa())
+}
+
+/// Reject accounts that are not owned by the Switchboard On-Demand program.
+pub fn check_feed_owner(feed: &AccountInfo) -> Result<()> {
+    if feed.owner != &ON_DEMAND_MAINNET_PID && feed.owner != &ON_DEMAND_DEVNET_PID {
+        msg!("Feed {} is owned by {}", feed.key(), feed.owner);
+        return Err(ErrorCode::InvalidFeedOwner.into());
+    }
+    Ok(())
+}
+
+// Reads of a single feed allowed per caller per slot through `rate_limited_read`.
+pub const MAX_READS_PER_SLOT: u32 = 4;
+
+#[program]
+pub mod sb_on_demand_rate_limit {
+    use super::*;
+
+    // Read a feed, allowing each caller at most MAX_READS_PER_SLOT reads of
+    // that feed per slot. The counter lives in a PDA keyed by (feed, caller),
+    // so one busy caller cannot use up the allowance of everyone else; it
+    // resets each slot.
+    pub fn rate_limited_read(ctx: Context<RateLimitedRead>, max_stale_slots: u64) -> Result<i128> {
+        let clock = Clock::get()?;
+        let limit = &mut ctx.accounts.rate_limit;
+        if limit.feed == Pubkey::default() {
+            // First read of this feed by this caller: the counter was just created.
+            limit.feed = ctx.accounts.feed.key();
+            limit.caller = ctx.accounts.caller.key();
+            limit.bump = ctx.bumps.rate_limit;
+        }
+        limit.record_read(clock.slot)?;
+        let value = feed_value(&ctx.accounts.feed, &clock, max_stale_slots)?;
+        msg!("price: {} (read {} of {})", value, limit.reads_this_slot, MAX_READS_PER_SLOT);
+        Ok(value)
+    }
+}
+
+// === Accounts ===
+#[account]
+pub struct RateLimit {
+    pub feed: Pubkey,
+    pub caller: Pubkey,       // Signer whose reads this counter limits
+    pub current_slot: u64,    // Slot reads_this_slot counts for
+    pub reads_this_slot: u32,
+    pub bump: u8,
+}
+
+impl RateLimit {
+    /// Count a read in `slot`, resetting the counter when the slot changes.
+    pub fn record_read(&mut self, slot: u64) -> Result<()> {
+        if self.current_slot != slot {
+            self.current_slot = slot;
+            self.reads_this_slot = 0;
+        }
+        if self.reads_this_slot >= MAX_READS_PER_SLOT {
+            msg!("{} reads already made in slot {}", self.reads_this_slot, slot);
+            return Err(ErrorCode::RateLimited.into());
+        }
+        self.reads_this_slot += 1;
+        Ok(())
+    }
+}
+
+// === Instructions ===
+#[derive(Accounts)]
+pub struct RateLimitedRead<'info> {
+    #[account(init_if_needed,
+        payer = caller,
+        seeds = [b"rateLimit".as_ref(), feed.key().as_ref(), caller.key().as_ref()],
+        space = 8 + 32 + 32 + 8 + 4 + 1,
+        bump)]
+    pub rate_limit: Account<'info, RateLimit>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// === Errors ===
+#[error_code]
+pub enum ErrorCode {
+    #[msg("The account is not a valid Switchboard pull feed.")]
+    InvalidFeedAccount,
+    #[msg("The feed value is stale.")]
+    StaleFeed,
+    #[msg("The feed account is not owned by the Switchboard program.")]
+    InvalidFeedOwner,
+    #[msg("The feed result is from a slot ahead of the current clock.")]
+    FeedFromFutureSlot,
+    #[msg("The per-slot read limit has been reached.")]
+    RateLimited,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limit_caps_reads_per_slot_and_resets() {
+        let mut limit = empty_rate_limit(Pubkey::new_unique(), Pubkey::new_unique());
+        for _ in 0..MAX_READS_PER_SLOT {
+            limit.record_read(10).unwrap();
+        }
+        assert_eq!(limit.record_read(10).err().unwrap(), Error::from(ErrorCode::RateLimited));
+        // The failed read is not counted, and a new slot starts a fresh allowance.
+        assert_eq!(limit.reads_this_slot, MAX_READS_PER_SLOT);
+        limit.record_read(11).unwrap();
+        assert_eq!((limit.current_slot, limit.reads_this_slot), (11, 1));
+    }
+
+    fn empty_rate_limit(feed: Pubkey, caller: Pubkey) -> RateLimit {
+        RateLimit { feed, caller, current_slot: 0, reads_this_slot: 0, bump: 0 }
+    }
+
+    #[test]
+    fn rate_limit_is_per_caller() {
+        let feed = Pubkey::new_unique();
+        let (busy, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let address = |caller: &Pubkey| {
+            Pubkey::find_program_address(&[b"rateLimit", feed.as_ref(), caller.as_ref()], &crate::ID).0
+        };
+        // Each caller of a feed gets its own counter account...
+        assert_ne!(address(&busy), address(&other));
+        let mut busy_limit = empty_rate_limit(feed, busy);
+        for _ in 0..MAX_READS_PER_SLOT {
+            busy_limit.record_read(10).unwrap();
+        }
+        assert!(busy_limit.record_read(10).is_err());
+        // ...so a caller exhausting its allowance does not starve anyone else.
+        let mut other_limit = empty_rate_limit(feed, other);
+        other_limit.record_read(10).unwrap();
+    }
+}
@@ -0,0 +1,34 @@
+use std::fs;
+
+// Version of switchboard-on-demand this example is written against.
+const EXPECTED_SWITCHBOARD_VERSION: &str = "0.1.12";
+
+// Record the switchboard-on-demand version resolved in Cargo.lock so the
+// program can report it, and warn at build time if it has drifted. Mixing
+// versions is the usual cause of `__Pubkey` vs `Pubkey` type errors.
+fn main() {
+    println!("cargo:rerun-if-changed=../../Cargo.lock");
+    let linked = fs::read_to_string("../../Cargo.lock")
+        .ok()
+        .and_then(|lock| {
+            let mut lines = lock.lines();
+            while let Some(line) = lines.next() {
+                if line == "name = \"switchboard-on-demand\"" {
+                    return lines
+                        .next()
+                        .and_then(|version| version.strip_prefix("version = \""))
+                        .map(|version| version.trim_end_matches('"').to_string());
+                }
+            }
+            None
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    if linked != EXPECTED_SWITCHBOARD_VERSION {
+        println!(
+            "cargo:warning=switchboard-on-demand {} is linked, but this example expects {}",
+            linked, EXPECTED_SWITCHBOARD_VERSION
+        );
+    }
+    println!("cargo:rustc-env=SWITCHBOARD_ON_DEMAND_VERSION={}", linked);
+    println!("cargo:rustc-env=SWITCHBOARD_ON_DEMAND_EXPECTED_VERSION={}", EXPECTED_SWITCHBOARD_VERSION);
+}
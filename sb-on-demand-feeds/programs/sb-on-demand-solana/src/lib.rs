@@ -1,23 +1,3454 @@
 use anchor_lang::prelude::*;
+use std::cell::Ref;
 use switchboard_on_demand::on_demand::accounts::pull_feed::PullFeedAccountData;
+use switchboard_on_demand::on_demand::accounts::queue::QueueAccountData;
+use switchboard_on_demand::program_id::{ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
 
 declare_id!("2uGHnRkDsupNnicE3btnqJbpus7DWKuniZcRmKAzHFv5");
 
+// Upper bound on the number of feeds a single instruction will process.
+pub const MAX_FEEDS: usize = 16;
+
+/// Parse a pull feed account, rejecting accounts not owned by Switchboard
+/// and accounts too short to hold one instead of letting the SDK panic on
+/// truncated data.
+pub fn load_feed<'a>(feed: &'a AccountInfo) -> Result<Ref<'a, PullFeedAccountData>> {
+    check_feed_owner(feed)?;
+    let feed_account = feed.data.borrow();
+    let min_len = 8 + std::mem::size_of::<PullFeedAccountData>();
+    if feed_account.len() < min_len {
+        msg!("Feed account holds {} bytes, expected at least {}", feed_account.len(), min_len);
+        return Err(ErrorCode::InvalidFeedAccount.into());
+    }
+    PullFeedAccountData::parse(feed_account).map_err(|e| {
+        msg!("Parse Error: {:?}", e);
+        ErrorCode::InvalidFeedAccount.into()
+    })
+}
+
+/// Parse a pull feed and return its median value as an 18-decimal fixed point
+/// integer, rejecting results older than `max_stale_slots`.
+pub fn feed_value(feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+    let feed = load_feed(feed)?;
+    // A result newer than the clock usually means a local test validator was
+    // never advanced; report it separately from ordinary staleness.
+    if feed.result.slot > clock.slot {
+        msg!(
+            "Feed result slot {} is ahead of the current slot {}; is the test clock behind?",
+            feed.result.slot,
+            clock.slot
+        );
+        return Err(ErrorCode::FeedFromFutureSlot.into());
+    }
+    let value = feed
+        .get_value(clock, max_stale_slots, 1, false)
+        .map_err(|e| {
+            msg!("Get Value Error: {:?}", e);
+            ErrorCode::StaleFeed
+        })?;
+    Ok(value.mantissa())
+}
+
+// Number of verification results kept in the VerificationLog ring buffer.
+pub const VERIFICATION_LOG_LEN: usize = 32;
+
+// Number of samples kept per feed in a PriceHistory account.
+pub const PRICE_HISTORY_LEN: usize = 64;
+
+// Bounds on the FeedNames registry.
+pub const MAX_FEED_NAMES: usize = 16;
+pub const MAX_SYMBOL_LEN: usize = 16;
+
+/// Return the current clock, optionally with its slot replaced by `override_slot`.
+/// Overrides are only honoured when built with the `clock-override` feature so
+/// they cannot be used against a production deployment.
+pub fn clock_with_override(override_slot: Option<u64>) -> Result<Clock> {
+    override_clock(Clock::get()?, override_slot)
+}
+
+/// `clock` with its slot replaced by `override_slot`; see `clock_with_override`.
+pub fn override_clock(mut clock: Clock, override_slot: Option<u64>) -> Result<Clock> {
+    if let Some(slot) = override_slot {
+        if !cfg!(feature = "clock-override") {
+            return Err(ErrorCode::ClockOverrideDisabled.into());
+        }
+        msg!("Overriding clock slot {} with {}", clock.slot, slot);
+        clock.slot = slot;
+    }
+    Ok(clock)
+}
+
+/// The feed's value and staleness as of `clock`, which may be a replayed
+/// historical slot.
+pub fn slot_reading(feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<SlotReading> {
+    let value = feed_value(feed, clock, max_stale_slots)?;
+    let staleness = clock.slot.saturating_sub(feed_result_slot(feed)?);
+    Ok(SlotReading { value, slot: clock.slot, staleness })
+}
+
+/// Reject accounts that are not owned by the Switchboard On-Demand program.
+pub fn check_feed_owner(feed: &AccountInfo) -> Result<()> {
+    if feed.owner != &ON_DEMAND_MAINNET_PID && feed.owner != &ON_DEMAND_DEVNET_PID {
+        msg!("Feed {} is owned by {}", feed.key(), feed.owner);
+        return Err(ErrorCode::InvalidFeedOwner.into());
+    }
+    Ok(())
+}
+
+/// Reject queue accounts that are not owned by the Switchboard On-Demand
+/// program or are too short to hold a queue, before the SDK slices into them.
+pub fn check_queue_account(queue: &AccountInfo) -> Result<()> {
+    if queue.owner != &ON_DEMAND_MAINNET_PID && queue.owner != &ON_DEMAND_DEVNET_PID {
+        msg!("Queue {} is owned by {}", queue.key(), queue.owner);
+        return Err(ErrorCode::InvalidQueueOwner.into());
+    }
+    let len = queue.data_len();
+    let min_len = 8 + std::mem::size_of::<QueueAccountData>();
+    if len < min_len {
+        msg!("Queue account holds {} bytes, expected at least {}", len, min_len);
+        return Err(ErrorCode::InvalidQueueAccount.into());
+    }
+    Ok(())
+}
+
+// Decimal scale of the values returned by `feed_value`.
+pub const FEED_DECIMALS: u32 = 18;
+
+/// Rescale a fixed point `value` with `from_decimals` decimal places to
+/// `to_decimals` decimal places, truncating toward zero when scaling down.
+pub fn normalize_decimals(value: i128, from_decimals: u32, to_decimals: u32) -> Result<i128> {
+    let shift = 10i128
+        .checked_pow(from_decimals.abs_diff(to_decimals))
+        .ok_or(ErrorCode::MathOverflow)?;
+    if from_decimals > to_decimals {
+        mul_div(value, 1, shift)
+    } else {
+        mul_div(value, shift, 1)
+    }
+}
+
+/// Average of `(value, decimals)` samples after rescaling each to `to_decimals`.
+pub fn normalized_average(samples: &[(i128, u32)], to_decimals: u32) -> Result<i128> {
+    require!(!samples.is_empty(), ErrorCode::NoFeedsProvided);
+    let mut sum: i128 = 0;
+    for &(value, decimals) in samples {
+        let normalized = normalize_decimals(value, decimals, to_decimals)?;
+        sum = sum.checked_add(normalized).ok_or(ErrorCode::MathOverflow)?;
+    }
+    Ok(sum / samples.len() as i128)
+}
+
+/// Parse a pull feed and return the hash of its job definitions.
+pub fn feed_hash(feed: &AccountInfo) -> Result<[u8; 32]> {
+    let feed = load_feed(feed)?;
+    Ok(feed.feed_hash)
+}
+
+/// Parse a pull feed and return the slot its current result was produced at.
+pub fn feed_result_slot(feed: &AccountInfo) -> Result<u64> {
+    let feed = load_feed(feed)?;
+    Ok(feed.result.slot)
+}
+
+// 10^FEED_DECIMALS, the fixed point scale of feed values.
+pub const SCALE: i128 = 1_000_000_000_000_000_000;
+
+/// `a * b / d` for unsigned values, computed with a 256-bit intermediate so
+/// the product cannot overflow. Returns None if `d` is zero or the quotient
+/// does not fit in a u128.
+fn mul_div_u128(a: u128, b: u128, d: u128) -> Option<u128> {
+    if d == 0 {
+        return None;
+    }
+    // 128 x 128 -> 256 bit product from 64-bit limbs.
+    const MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_hi = a_hi * b_hi;
+    let cross = (lo_lo >> 64) + (hi_lo & MASK) + (lo_hi & MASK);
+    let lo = (cross << 64) | (lo_lo & MASK);
+    let hi = hi_hi + (hi_lo >> 64) + (lo_hi >> 64) + (cross >> 64);
+    if hi >= d {
+        return None;
+    }
+    // Shift-subtract long division of hi:lo by d; the remainder stays below d.
+    let mut remainder = hi;
+    let mut quotient = 0u128;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> bit) & 1);
+        if carry == 1 || remainder >= d {
+            remainder = remainder.wrapping_sub(d);
+            quotient |= 1 << bit;
+        }
+    }
+    Some(quotient)
+}
+
+/// `a * b / d`, truncated toward zero like `/`, without overflowing on the
+/// intermediate product. Use it to rescale 18-decimal values, e.g.
+/// `mul_div(numerator, SCALE, denominator)` for a fixed point ratio.
+pub fn mul_div(a: i128, b: i128, d: i128) -> Result<i128> {
+    require!(d != 0, ErrorCode::DivideByZero);
+    let negative = (a < 0) ^ (b < 0) ^ (d < 0);
+    let magnitude = mul_div_u128(a.unsigned_abs(), b.unsigned_abs(), d.unsigned_abs())
+        .ok_or(ErrorCode::MathOverflow)?;
+    if negative {
+        0i128.checked_sub_unsigned(magnitude).ok_or(ErrorCode::MathOverflow.into())
+    } else {
+        i128::try_from(magnitude).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+}
+
+// Domain separation prefixes, so an internal node can never be presented as a leaf.
+pub const MERKLE_LEAF_PREFIX: u8 = 0x00;
+pub const MERKLE_NODE_PREFIX: u8 = 0x01;
+
+// Longest proof a tree of at most MAX_FEEDS leaves needs: ceil(log2(MAX_FEEDS)).
+pub const MAX_MERKLE_PROOF_LEN: usize = 4;
+
+/// Leaf committed to by `feed_merkle_root`:
+/// sha256(0x00 || feed_id || value (LE) || slot (LE)).
+pub fn feed_leaf(feed_id: &[u8; 32], value: i128, slot: u64) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[
+        &[MERKLE_LEAF_PREFIX],
+        feed_id,
+        &value.to_le_bytes(),
+        &slot.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Parent of two Merkle nodes: sha256(0x01 || left || right).
+pub fn merkle_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[&[MERKLE_NODE_PREFIX], left, right]).to_bytes()
+}
+
+/// Merkle root over `leaves`. An odd node at the end of a level is promoted
+/// unchanged rather than paired with itself, so `[a, b, c]` and `[a, b, c, c]`
+/// commit to different roots.
+pub fn merkle_root(mut level: Vec<[u8; 32]>) -> [u8; 32] {
+    if level.is_empty() {
+        return [0u8; 32];
+    }
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => merkle_node(left, right),
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    level[0]
+}
+
+/// Check that `leaf` sits at `index` in a tree of `leaf_count` leaves under
+/// `root`, given its sibling hashes from the bottom of the tree up. Levels
+/// where the node is promoted consume no sibling, and the proof must be used
+/// up exactly.
+pub fn verify_merkle_proof(
+    leaf: [u8; 32],
+    proof: &[[u8; 32]],
+    mut index: usize,
+    leaf_count: usize,
+    root: [u8; 32],
+) -> bool {
+    if index >= leaf_count || proof.len() > MAX_MERKLE_PROOF_LEN {
+        return false;
+    }
+    let mut siblings = proof.iter();
+    let mut node = leaf;
+    let mut width = leaf_count;
+    while width > 1 {
+        if index % 2 == 1 {
+            let Some(sibling) = siblings.next() else { return false };
+            node = merkle_node(sibling, &node);
+        } else if index + 1 < width {
+            let Some(sibling) = siblings.next() else { return false };
+            node = merkle_node(&node, sibling);
+        }
+        index /= 2;
+        width = (width + 1) / 2;
+    }
+    siblings.next().is_none() && node == root
+}
+
+// Queues whose feeds this program accepts: the mainnet and devnet default queues.
+pub const ALLOWED_QUEUES: &[Pubkey] = &[
+    anchor_lang::solana_program::pubkey!("A43DyUGA7s8eXPxqEjJY6EBu1KKbNgfxF8h17VAHn13w"),
+    anchor_lang::solana_program::pubkey!("FfD96yeXs4cxZshoPPSKhSPgVQxLAJUT3gefgh84m1Di"),
+];
+
+/// Reject feeds that are not served by one of ALLOWED_QUEUES.
+pub fn check_feed_queue(feed: &AccountInfo) -> Result<()> {
+    let feed = load_feed(feed)?;
+    if !ALLOWED_QUEUES.contains(&feed.queue) {
+        msg!("Feed queue {} is not approved", feed.queue);
+        return Err(ErrorCode::UnapprovedQueue.into());
+    }
+    Ok(())
+}
+
+/// The 8-byte discriminator Anchor prefixes to instruction `name`'s data.
+pub fn instruction_discriminator(name: &str) -> [u8; 8] {
+    let hash = anchor_lang::solana_program::hash::hash(format!("global:{}", name).as_bytes());
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash.to_bytes()[..8]);
+    discriminator
+}
+
+/// Error unless `a` and `b` differ by at most `tolerance_bps` basis points of `a`.
+pub fn require_agreement(a: i128, b: i128, tolerance_bps: u16) -> Result<()> {
+    let diff = a.checked_sub(b).ok_or(ErrorCode::MathOverflow)?.unsigned_abs();
+    let allowed = a.unsigned_abs().checked_mul(tolerance_bps as u128).ok_or(ErrorCode::MathOverflow)?;
+    let scaled_diff = diff.checked_mul(10_000).ok_or(ErrorCode::MathOverflow)?;
+    if scaled_diff > allowed {
+        msg!("Values {} and {} differ by more than {} bps", a, b, tolerance_bps);
+        return Err(ErrorCode::SourcesDisagree.into());
+    }
+    Ok(())
+}
+
+/// How fresh a feed result must be for a read to succeed.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FreshnessPolicy {
+    /// Reject results older than the given number of slots.
+    Strict(u64),
+    /// Defer to the `max_staleness` configured on the feed itself.
+    BestEffort,
+    /// Only accept a result produced in the current slot.
+    RequireFresh,
+}
+
+/// Read a feed value, enforcing `policy`.
+pub fn feed_value_with_policy(feed: &AccountInfo, clock: &Clock, policy: FreshnessPolicy) -> Result<i128> {
+    let max_stale_slots = match policy {
+        FreshnessPolicy::Strict(max_stale_slots) => max_stale_slots,
+        FreshnessPolicy::BestEffort => load_feed(feed)?.max_staleness as u64,
+        FreshnessPolicy::RequireFresh => {
+            let result_slot = feed_result_slot(feed)?;
+            if result_slot != clock.slot {
+                msg!("Feed result slot {} is not the current slot {}", result_slot, clock.slot);
+                return Err(ErrorCode::StaleFeed.into());
+            }
+            0
+        }
+    };
+    feed_value(feed, clock, max_stale_slots)
+}
+
+/// `#[access_control]` guard: fail before the handler runs unless `feed` is an
+/// on-demand feed with a result no older than `max_stale_slots`.
+pub fn verify_fresh_oracle(feed: &AccountInfo, max_stale_slots: u64) -> Result<()> {
+    let clock = Clock::get()?;
+    feed_value(feed, &clock, max_stale_slots)?;
+    Ok(())
+}
+
+/// The feed `value_with_fallback` took its price from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceSource {
+    Primary,
+    Secondary,
+}
+
+/// Read `primary`, falling back to `secondary` only when the primary is stale.
+/// A spoofed or malformed primary is rejected outright.
+pub fn value_with_fallback(
+    primary: &AccountInfo,
+    secondary: Option<&AccountInfo>,
+    clock: &Clock,
+    max_stale_slots: u64,
+) -> Result<(i128, PriceSource)> {
+    match feed_value(primary, clock, max_stale_slots) {
+        Ok(price) => return Ok((price, PriceSource::Primary)),
+        Err(e) if e == Error::from(ErrorCode::StaleFeed) => msg!("Primary feed is stale"),
+        Err(e) => return Err(e),
+    }
+    let Some(secondary) = secondary else {
+        msg!("No secondary feed supplied");
+        return Err(ErrorCode::StaleFeed.into());
+    };
+    Ok((feed_value(secondary, clock, max_stale_slots)?, PriceSource::Secondary))
+}
+
+/// Values of `feeds` in the order of `feed_ids`, with 0 for any id none of
+/// the feeds carries.
+pub fn read_feeds_by_id(
+    feeds: &[AccountInfo],
+    feed_ids: Vec<[u8; 32]>,
+    clock: &Clock,
+    max_stale_slots: u64,
+) -> Result<Vec<FeedReading>> {
+    let mut available: Vec<([u8; 32], i128)> = Vec::with_capacity(feeds.len());
+    for account in feeds.iter() {
+        available.push((feed_hash(account)?, feed_value(account, clock, max_stale_slots)?));
+    }
+    let readings = feed_ids
+        .into_iter()
+        .map(|feed_id| {
+            let value = available
+                .iter()
+                .find(|(hash, _)| *hash == feed_id)
+                .map(|(_, value)| *value)
+                .unwrap_or(0);
+            FeedReading { feed_id, value }
+        })
+        .collect();
+    Ok(readings)
+}
+
+/// Median value of a variable number of feeds, each owner-checked and
+/// supplied at most once.
+pub fn median_of_feeds(feeds: &[AccountInfo], clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+    require!(!feeds.is_empty(), ErrorCode::NoFeedsProvided);
+    require!(feeds.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+    let mut values = Vec::with_capacity(feeds.len());
+    for (i, feed) in feeds.iter().enumerate() {
+        if feeds[..i].iter().any(|other| other.key == feed.key) {
+            msg!("Feed {} supplied more than once", feed.key());
+            return Err(ErrorCode::DuplicateFeed.into());
+        }
+        let value = feed_value(feed, clock, max_stale_slots)?;
+        msg!("feed: {}, value: {}", feed.key(), value);
+        values.push(value);
+    }
+    values.sort_unstable();
+    Ok(values[values.len() / 2])
+}
+
+/// Value of `feeds[index]`, which must exist and carry `expected_id`.
+pub fn feed_value_at(
+    feeds: &[AccountInfo],
+    index: u8,
+    expected_id: [u8; 32],
+    clock: &Clock,
+    max_stale_slots: u64,
+) -> Result<i128> {
+    let feed = feeds.get(index as usize).ok_or(ErrorCode::FeedIndexOutOfRange)?;
+    if feed_hash(feed)? != expected_id {
+        msg!("Feed at index {} does not match the expected feed id", index);
+        return Err(ErrorCode::UnexpectedFeed.into());
+    }
+    feed_value(feed, clock, max_stale_slots)
+}
+
+/// The `on_feed_update` instruction `stream_feeds` sends to `callback_program`
+/// for each feed, with `callback_state` as its only account.
+pub fn feed_callback_ix(
+    callback_program: Pubkey,
+    callback_state: Pubkey,
+    update: &FeedCallback,
+) -> Result<anchor_lang::solana_program::instruction::Instruction> {
+    let mut data = instruction_discriminator("on_feed_update").to_vec();
+    update.serialize(&mut data)?;
+    Ok(anchor_lang::solana_program::instruction::Instruction {
+        program_id: callback_program,
+        accounts: vec![AccountMeta::new(callback_state, false)],
+        data,
+    })
+}
+
+/// The ids and values of `feeds` as sent to an event logger, stamped with the
+/// current slot.
+pub fn feed_results(feeds: &[AccountInfo], clock: &Clock, max_stale_slots: u64) -> Result<FeedResults> {
+    require!(feeds.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+    let mut readings = Vec::with_capacity(feeds.len());
+    for feed in feeds.iter() {
+        readings.push(FeedReading {
+            feed_id: feed_hash(feed)?,
+            value: feed_value(feed, clock, max_stale_slots)?,
+        });
+    }
+    Ok(FeedResults { readings, slot: clock.slot })
+}
+
+/// The `log_feed_results` instruction `log_to_event_bus` sends to
+/// `logger_program`, with `logger_state` as its only account.
+pub fn event_logger_ix(
+    logger_program: Pubkey,
+    logger_state: Pubkey,
+    results: &FeedResults,
+) -> Result<anchor_lang::solana_program::instruction::Instruction> {
+    let mut data = instruction_discriminator("log_feed_results").to_vec();
+    results.serialize(&mut data)?;
+    Ok(anchor_lang::solana_program::instruction::Instruction {
+        program_id: logger_program,
+        accounts: vec![AccountMeta::new(logger_state, false)],
+        data,
+    })
+}
+
+/// Slots left before a result from `result_slot` exceeds `max_stale_slots` at
+/// `current_slot`; negative once it is already stale.
+pub fn slots_remaining(result_slot: u64, current_slot: u64, max_stale_slots: u64) -> i64 {
+    let age = current_slot.saturating_sub(result_slot);
+    (max_stale_slots as i128 - age as i128).clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+/// The result slot shared by every feed in `feeds`, each of which must also
+/// be fresh; InconsistentSlots if any two differ.
+pub fn common_result_slot(feeds: &[AccountInfo], clock: &Clock, max_stale_slots: u64) -> Result<u64> {
+    require!(!feeds.is_empty(), ErrorCode::NoFeedsProvided);
+    require!(feeds.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+    let expected_slot = feed_result_slot(&feeds[0])?;
+    for feed in feeds.iter() {
+        feed_value(feed, clock, max_stale_slots)?;
+        let slot = feed_result_slot(feed)?;
+        if slot != expected_slot {
+            msg!("Feed {} sampled at slot {}, expected {}", feed.key(), slot, expected_slot);
+            return Err(ErrorCode::InconsistentSlots.into());
+        }
+    }
+    Ok(expected_slot)
+}
+
+/// The slots a feed's staleness checks are based on, as logged by `debug_slots`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SlotDiagnostics {
+    pub clock_slot: u64,
+    pub result_slot: u64,
+    pub staleness: i128, // clock_slot - result_slot; negative for a result ahead of the clock
+    pub max_staleness: u32,
+}
+
+pub fn slot_diagnostics(feed: &AccountInfo, clock: &Clock) -> Result<SlotDiagnostics> {
+    let feed = load_feed(feed)?;
+    Ok(SlotDiagnostics {
+        clock_slot: clock.slot,
+        result_slot: feed.result.slot,
+        staleness: clock.slot as i128 - feed.result.slot as i128,
+        max_staleness: feed.max_staleness,
+    })
+}
+
+/// The feed's value, requiring at least `min_samples` oracle submissions within
+/// `max_stale_slots`.
+pub fn value_with_min_samples(
+    feed: &AccountInfo,
+    clock: &Clock,
+    min_samples: u32,
+    max_stale_slots: u64,
+) -> Result<i128> {
+    // Owner, future-slot and staleness checks first, so a stale feed is
+    // reported as StaleFeed rather than as missing signatures.
+    feed_value(feed, clock, max_stale_slots)?;
+    let feed = load_feed(feed)?;
+    let value = feed
+        .get_value(clock, max_stale_slots, min_samples, false)
+        .map_err(|e| {
+            msg!("Fewer than {} fresh oracle signatures: {:?}", min_samples, e);
+            ErrorCode::InsufficientSignatures
+        })?
+        .mantissa();
+    Ok(value)
+}
+
+/// The weighted sum of the feeds' values, with `weights` in basis points
+/// summing to 10_000 and each naming one of `feeds` by id.
+pub fn basket_index(
+    feeds: &[AccountInfo],
+    weights: &[BasketWeight],
+    clock: &Clock,
+    max_stale_slots: u64,
+) -> Result<i128> {
+    require!(weights.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+    let total_bps = weights
+        .iter()
+        .try_fold(0u64, |total, w| total.checked_add(w.weight_bps))
+        .ok_or(ErrorCode::InvalidWeights)?;
+    if total_bps != 10_000 {
+        msg!("Weights sum to {} bps, expected 10000", total_bps);
+        return Err(ErrorCode::InvalidWeights.into());
+    }
+    let mut available: Vec<([u8; 32], &AccountInfo)> = Vec::with_capacity(feeds.len());
+    for feed in feeds.iter() {
+        available.push((feed_hash(feed)?, feed));
+    }
+
+    let mut index: i128 = 0;
+    for weight in weights.iter() {
+        let (_, feed) = available
+            .iter()
+            .find(|(hash, _)| *hash == weight.feed_id)
+            .ok_or(ErrorCode::MissingFeed)?;
+        let value = feed_value(feed, clock, max_stale_slots)?;
+        let contribution = value
+            .checked_mul(weight.weight_bps as i128)
+            .map(|weighted| weighted / 10_000)
+            .ok_or(ErrorCode::MathOverflow)?;
+        index = index.checked_add(contribution).ok_or(ErrorCode::MathOverflow)?;
+    }
+    Ok(index)
+}
+
+/// Each feed's value together with the staleness limit it was held to: its
+/// entry in `max_ages`, or `default_max_stale_slots` if unlisted.
+pub fn values_with_max_ages(
+    feeds: &[AccountInfo],
+    max_ages: &[FeedMaxAge],
+    default_max_stale_slots: u64,
+    clock: &Clock,
+) -> Result<Vec<(i128, u64)>> {
+    require!(feeds.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+    let mut values = Vec::with_capacity(feeds.len());
+    for feed in feeds.iter() {
+        let feed_id = feed_hash(feed)?;
+        let max_stale_slots = max_ages
+            .iter()
+            .find(|max_age| max_age.feed_id == feed_id)
+            .map(|max_age| max_age.max_stale_slots)
+            .unwrap_or(default_max_stale_slots);
+        let value = feed_value(feed, clock, max_stale_slots).map_err(|e| {
+            msg!("Feed {} failed its {} slot limit", feed.key(), max_stale_slots);
+            e
+        })?;
+        values.push((value, max_stale_slots));
+    }
+    Ok(values)
+}
+
+/// The trades, in native units with positive meaning buy, that bring asset A
+/// to `target_a_bps` of a two-asset portfolio's value. Prices have 18 decimals.
+pub fn rebalance(
+    holdings_a: u64,
+    decimals_a: u8,
+    price_a: i128,
+    holdings_b: u64,
+    decimals_b: u8,
+    price_b: i128,
+    target_a_bps: u16,
+) -> Result<RebalanceResult> {
+    require!(target_a_bps <= 10_000, ErrorCode::InvalidWeights);
+    require!(price_a > 0 && price_b > 0, ErrorCode::DivideByZero);
+    let unit_a = 10i128.checked_pow(decimals_a as u32).ok_or(ErrorCode::MathOverflow)?;
+    let unit_b = 10i128.checked_pow(decimals_b as u32).ok_or(ErrorCode::MathOverflow)?;
+
+    let value_a = mul_div(holdings_a as i128, price_a, unit_a)?;
+    let value_b = mul_div(holdings_b as i128, price_b, unit_b)?;
+    let total = value_a.checked_add(value_b).ok_or(ErrorCode::MathOverflow)?;
+    let target_a = total
+        .checked_mul(target_a_bps as i128)
+        .map(|value| value / 10_000)
+        .ok_or(ErrorCode::MathOverflow)?;
+    // Value moved into A is taken out of B, and vice versa.
+    let delta_value = target_a.checked_sub(value_a).ok_or(ErrorCode::MathOverflow)?;
+    let trade_a = mul_div(delta_value, unit_a, price_a)?;
+    let trade_b = mul_div(delta_value, unit_b, price_b)?
+        .checked_neg()
+        .ok_or(ErrorCode::MathOverflow)?;
+    let weight_a_bps = if total == 0 {
+        0
+    } else {
+        value_a.checked_mul(10_000).ok_or(ErrorCode::MathOverflow)? / total
+    };
+    Ok(RebalanceResult {
+        value_a,
+        value_b,
+        weight_a_bps: weight_a_bps as u64,
+        trade_a,
+        trade_b,
+    })
+}
+
+/// Median of `feeds`, leaving out any whose feed hash is in `skip_feeds`,
+/// together with the number of feeds skipped.
+pub fn median_skipping(
+    feeds: &[AccountInfo],
+    skip_feeds: &[[u8; 32]],
+    clock: &Clock,
+    max_stale_slots: u64,
+) -> Result<(i128, usize)> {
+    require!(feeds.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+    let mut values = Vec::with_capacity(feeds.len());
+    for (i, feed) in feeds.iter().enumerate() {
+        if feeds[..i].iter().any(|other| other.key == feed.key) {
+            msg!("Feed {} supplied more than once", feed.key());
+            return Err(ErrorCode::DuplicateFeed.into());
+        }
+        if skip_feeds.contains(&feed_hash(feed)?) {
+            msg!("Skipping feed {}", feed.key());
+            continue;
+        }
+        let value = feed_value(feed, clock, max_stale_slots)?;
+        msg!("feed: {}, value: {}", feed.key(), value);
+        values.push(value);
+    }
+    require!(!values.is_empty(), ErrorCode::NoFeedsProvided);
+    values.sort_unstable();
+    Ok((values[values.len() / 2], feeds.len() - values.len()))
+}
+
+/// The feed's value, provided its result was produced at or after `min_slot`
+/// and is no older than `max_stale_slots`.
+pub fn value_after_slot(feed: &AccountInfo, clock: &Clock, min_slot: u64, max_stale_slots: u64) -> Result<i128> {
+    let result_slot = feed_result_slot(feed)?;
+    if result_slot < min_slot {
+        msg!("Feed result slot {} is before min_slot {}", result_slot, min_slot);
+        return Err(ErrorCode::ResultBeforeMinSlot.into());
+    }
+    feed_value(feed, clock, max_stale_slots)
+}
+
+/// Freshness of `feeds` as reported to monitoring bots: the age of the oldest
+/// result, and whether every result is within `max_stale_slots`.
+pub fn health_status(feeds: &[AccountInfo], clock: &Clock, max_stale_slots: u64) -> Result<HealthStatus> {
+    require!(!feeds.is_empty(), ErrorCode::NoFeedsProvided);
+    require!(feeds.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+    let mut staleness = 0;
+    for feed in feeds.iter() {
+        let age = clock.slot.saturating_sub(feed_result_slot(feed)?);
+        staleness = staleness.max(age);
+    }
+    Ok(HealthStatus {
+        slot: clock.slot,
+        staleness,
+        feed_count: feeds.len() as u8,
+        all_fresh: staleness <= max_stale_slots,
+    })
+}
+
+/// Readings of `feeds`, whose feed hashes must be exactly `expected_feed_ids`
+/// in any order; UnexpectedFeeds if any are missing or extra.
+pub fn read_expected_feeds(
+    feeds: &[AccountInfo],
+    expected_feed_ids: &[[u8; 32]],
+    clock: &Clock,
+    max_stale_slots: u64,
+) -> Result<Vec<FeedReading>> {
+    require!(feeds.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+    let mut supplied = Vec::with_capacity(feeds.len());
+    for feed in feeds.iter() {
+        let feed_id = feed_hash(feed)?;
+        if supplied.contains(&feed_id) {
+            msg!("Feed {} supplied more than once", feed.key());
+            return Err(ErrorCode::DuplicateFeed.into());
+        }
+        supplied.push(feed_id);
+    }
+    let missing = expected_feed_ids.iter().filter(|id| !supplied.contains(id)).count();
+    let extra = supplied.iter().filter(|id| !expected_feed_ids.contains(id)).count();
+    if missing > 0 || extra > 0 || expected_feed_ids.len() != supplied.len() {
+        msg!("{} expected feeds missing, {} unexpected feeds supplied", missing, extra);
+        return Err(ErrorCode::UnexpectedFeeds.into());
+    }
+
+    let mut readings = Vec::with_capacity(feeds.len());
+    for (feed, feed_id) in feeds.iter().zip(supplied.into_iter()) {
+        let value = feed_value(feed, clock, max_stale_slots)?;
+        readings.push(FeedReading { feed_id, value });
+    }
+    Ok(readings)
+}
+
+/// Read a bid and an ask feed; InvariantViolated if the bid is above the ask.
+pub fn read_bid_ask_pair(
+    bid_feed: &AccountInfo,
+    ask_feed: &AccountInfo,
+    clock: &Clock,
+    max_stale_slots: u64,
+) -> Result<BidAsk> {
+    let bid = feed_value(bid_feed, clock, max_stale_slots)?;
+    let ask = feed_value(ask_feed, clock, max_stale_slots)?;
+    if bid > ask {
+        msg!("bid {} is above ask {}", bid, ask);
+        return Err(ErrorCode::InvariantViolated.into());
+    }
+    Ok(BidAsk { bid, ask })
+}
+
+/// Signed deviation of `price` (18 decimals) from a 1.0 peg in basis points,
+/// flagged as depegged beyond `depeg_threshold_bps`.
+pub fn peg_status(price: i128, depeg_threshold_bps: u16) -> Result<PegStatus> {
+    let deviation_bps = price
+        .checked_sub(SCALE)
+        .and_then(|diff| diff.checked_mul(10_000))
+        .map(|diff| diff / SCALE)
+        .and_then(|bps| i64::try_from(bps).ok())
+        .ok_or(ErrorCode::MathOverflow)?;
+    let depegged = deviation_bps.unsigned_abs() > depeg_threshold_bps as u64;
+    Ok(PegStatus { price, deviation_bps, depegged })
+}
+
+// Seconds in a 365 day year, used to express times to expiry in years.
+pub const SECONDS_PER_YEAR: i128 = 365 * 24 * 60 * 60;
+
+/// Convert a floating point price into a fixed point integer with `decimals`
+/// decimal places, e.g. `to_scaled(1.5, 18) == 1_500_000_000_000_000_000`.
+/// Rounds half away from zero; values outside the i128 range saturate.
+/// Intended for clients and tests; on-chain code should stay in fixed point.
+pub fn to_scaled(price: f64, decimals: u32) -> i128 {
+    (price * 10f64.powi(decimals as i32)).round() as i128
+}
+
+/// Convert a fixed point integer with `decimals` decimal places back into a
+/// float. Exact up to f64 precision (about 15 significant digits).
+pub fn from_scaled(value: i128, decimals: u32) -> f64 {
+    value as f64 / 10f64.powi(decimals as i32)
+}
+
+/// Output the oracle price implies for a swap of `amount_in` at `price` (18
+/// decimals), rejected if it falls below the caller's `min_amount_out`.
+pub fn check_swap_output(amount_in: u64, price: i128, min_amount_out: u64) -> Result<i128> {
+    let expected_out = mul_div(amount_in as i128, price, SCALE)?;
+    if expected_out < min_amount_out as i128 {
+        msg!("Oracle implies {} out, below the minimum of {}", expected_out, min_amount_out);
+        return Err(ErrorCode::OracleGuardrail.into());
+    }
+    Ok(expected_out)
+}
+
+// A transaction is only processed while its recent blockhash is at most this
+// many slots old (the runtime's MAX_PROCESSING_AGE).
+pub const BLOCKHASH_MAX_AGE_SLOTS: u64 = 150;
+
 #[program]
 pub mod sb_on_demand_solana {
     use super::*;
 
     pub fn test<'a>(ctx: Context<Test>) -> Result<()> {
-        let feed_account = ctx.accounts.feed.data.borrow();
         // Docs at: https://switchboard-on-demand-rust-docs.web.app/on_demand/accounts/pull_feed/struct.PullFeedAccountData.html
-        let feed = PullFeedAccountData::parse(feed_account).unwrap();
+        let feed = load_feed(&ctx.accounts.feed)?;
         msg!("price: {:?}", feed.value());
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct Test<'info> {
-    /// CHECK: via switchboard sdk
-    pub feed: AccountInfo<'info>,
+    // Report the size and configuration of a queue so integrators can check
+    // its health before relying on feeds that are served by it.
+    pub fn queue_info(ctx: Context<QueueInfo>) -> Result<QueueSummary> {
+        check_queue_account(&ctx.accounts.queue)?;
+        let queue_account = ctx.accounts.queue.data.borrow();
+        let queue = QueueAccountData::new_from_bytes(&queue_account).map_err(|e| {
+            msg!("Parse Error: {:?}", e);
+            ErrorCode::InvalidQueueAccount
+        })?;
+        let summary = QueueSummary {
+            oracle_count: queue.oracle_keys_len,
+            max_quote_verification_age: queue.max_quote_verification_age,
+            node_timeout: queue.node_timeout,
+            oracle_min_stake: queue.oracle_min_stake,
+            last_heartbeat: queue.last_heartbeat,
+        };
+        msg!("oracles: {}", summary.oracle_count);
+        msg!("max_quote_verification_age: {}", summary.max_quote_verification_age);
+        msg!("node_timeout: {}", summary.node_timeout);
+        msg!("oracle_min_stake: {}", summary.oracle_min_stake);
+        msg!("last_heartbeat: {}", summary.last_heartbeat);
+        Ok(summary)
+    }
+
+    // Read the Switchboard feed and, if it is older than `max_stale_slots`,
+    // fall back to a secondary feed (e.g. one created with `buildPythnetJob`).
+    // Both feeds are read through `feed_value`; only staleness triggers the
+    // fallback, a spoofed or malformed primary is rejected outright.
+    pub fn read_with_fallback(ctx: Context<ReadWithFallback>, max_stale_slots: u64) -> Result<i128> {
+        let (price, source) = value_with_fallback(
+            &ctx.accounts.feed,
+            ctx.accounts.secondary_feed.as_ref(),
+            &Clock::get()?,
+            max_stale_slots,
+        )?;
+        msg!("source: {:?}", source);
+        msg!("price: {}", price);
+        Ok(price)
+    }
+
+    // Read several feeds, passed as remaining accounts, in one call. Values are
+    // returned in the order of `feed_ids`, with 0 for any id not supplied.
+    pub fn read_many<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReadMany>,
+        feed_ids: Vec<[u8; 32]>,
+        max_stale_slots: u64,
+    ) -> Result<Vec<FeedReading>> {
+        require!(feed_ids.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+        read_feeds_by_id(ctx.remaining_accounts, feed_ids, &Clock::get()?, max_stale_slots)
+    }
+
+    pub fn init_verification_log(ctx: Context<InitVerificationLog>) -> Result<()> {
+        let log = &mut ctx.accounts.verification_log;
+        log.next_index = 0;
+        log.count = 0;
+        log.bump = ctx.bumps.verification_log;
+        Ok(())
+    }
+
+    // Read the feed and record the outcome in the ring buffer. A failed read is
+    // recorded rather than returned as an error so that it persists on-chain.
+    pub fn verify_and_record(ctx: Context<VerifyAndRecord>, max_stale_slots: u64) -> Result<()> {
+        // Failed reads are logged rather than returned, so reject non-Switchboard
+        // accounts up front instead of recording them.
+        check_feed_owner(&ctx.accounts.feed)?;
+        let clock = Clock::get()?;
+        let result_slot = match load_feed(&ctx.accounts.feed) {
+            Ok(feed) => Some(feed.result.slot),
+            Err(e) => {
+                msg!("Feed could not be loaded: {:?}", e);
+                None
+            }
+        };
+        let success = feed_value(&ctx.accounts.feed, &clock, max_stale_slots).is_ok();
+        let record = VerificationRecord::new(clock.slot, result_slot, success);
+        msg!("slot: {}, staleness: {:?}, success: {}", record.slot, record.staleness, record.success);
+        ctx.accounts.verification_log.push(record);
+        Ok(())
+    }
+
+    // Return the recorded verification results, oldest first.
+    pub fn get_verification_log(ctx: Context<GetVerificationLog>) -> Result<Vec<VerificationRecord>> {
+        Ok(ctx.accounts.verification_log.records())
+    }
+
+    // Read the feed as of `override_slot` instead of the current slot, which makes
+    // staleness edge cases easy to exercise in local tests and lets old results
+    // be replayed for backtesting. Staleness is reported relative to that slot.
+    pub fn read_at_slot(
+        ctx: Context<ReadAtSlot>,
+        max_stale_slots: u64,
+        override_slot: Option<u64>,
+    ) -> Result<SlotReading> {
+        let clock = clock_with_override(override_slot)?;
+        let reading = slot_reading(&ctx.accounts.feed, &clock, max_stale_slots)?;
+        msg!("price: {}", reading.value);
+        msg!("staleness at slot {}: {}", reading.slot, reading.staleness);
+        Ok(reading)
+    }
+
+    // Aggregate a variable number of feeds passed as remaining accounts and
+    // return their median value.
+    pub fn aggregate_feeds<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AggregateFeeds>,
+        max_stale_slots: u64,
+    ) -> Result<i128> {
+        let feeds = ctx.remaining_accounts;
+        let median = median_of_feeds(feeds, &Clock::get()?, max_stale_slots)?;
+        msg!("median of {} feeds: {}", feeds.len(), median);
+        Ok(median)
+    }
+
+    // Average feeds whose results are expressed at different decimal scales.
+    // `decimals[i]` is the number of raw decimals reported by the i-th remaining
+    // account; the average is returned with FEED_DECIMALS decimals.
+    pub fn aggregate_normalized<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AggregateFeeds>,
+        decimals: Vec<u8>,
+        max_stale_slots: u64,
+    ) -> Result<i128> {
+        let feeds = ctx.remaining_accounts;
+        require!(!feeds.is_empty(), ErrorCode::NoFeedsProvided);
+        require!(feeds.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+        require!(decimals.len() == feeds.len(), ErrorCode::DecimalsMismatch);
+        let clock = Clock::get()?;
+        let mut samples = Vec::with_capacity(feeds.len());
+        for (i, (feed, feed_decimals)) in feeds.iter().zip(decimals.iter()).enumerate() {
+            if feeds[..i].iter().any(|other| other.key == feed.key) {
+                msg!("Feed {} supplied more than once", feed.key());
+                return Err(ErrorCode::DuplicateFeed.into());
+            }
+            // feed_value adds FEED_DECIMALS on top of the decimals the job reports.
+            let value = feed_value(feed, &clock, max_stale_slots)?;
+            msg!("feed: {}, value: {}, decimals: {}", feed.key(), value, feed_decimals);
+            samples.push((value, FEED_DECIMALS + *feed_decimals as u32));
+        }
+        let average = normalized_average(&samples, FEED_DECIMALS)?;
+        msg!("normalized average: {}", average);
+        Ok(average)
+    }
+
+    // Fail fast if the feed at position `index` of the remaining accounts is not
+    // the one the program expects, e.g. after a client changes its feed layout.
+    pub fn assert_feed_at<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AssertFeedAt>,
+        index: u8,
+        expected_id: [u8; 32],
+        max_stale_slots: u64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let value = feed_value_at(ctx.remaining_accounts, index, expected_id, &clock, max_stale_slots)?;
+        msg!("feed[{}]: {}", index, value);
+        Ok(())
+    }
+
+    // Forward each verified feed value to a caller-supplied program so it can
+    // react without polling. The receiver must implement an Anchor instruction
+    // `on_feed_update(update: FeedCallback)` taking `callback_state` as its only account.
+    pub fn stream_feeds<'info>(
+        ctx: Context<'_, '_, 'info, 'info, StreamFeeds<'info>>,
+        max_stale_slots: u64,
+    ) -> Result<()> {
+        let feeds = ctx.remaining_accounts;
+        require!(feeds.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+        let clock = Clock::get()?;
+        for feed in feeds.iter() {
+            let update = FeedCallback {
+                feed: feed.key(),
+                value: feed_value(feed, &clock, max_stale_slots)?,
+                slot: clock.slot,
+            };
+            let ix = feed_callback_ix(
+                ctx.accounts.callback_program.key(),
+                ctx.accounts.callback_state.key(),
+                &update,
+            )?;
+            anchor_lang::solana_program::program::invoke(
+                &ix,
+                &[
+                    ctx.accounts.callback_state.to_account_info(),
+                    ctx.accounts.callback_program.to_account_info(),
+                ],
+            )?;
+            msg!("Streamed feed {} to {}", update.feed, ix.program_id);
+        }
+        Ok(())
+    }
+
+    // Return how many slots remain before the feed result exceeds `max_stale_slots`;
+    // negative once it is already stale. Keepers can use this to schedule updates.
+    pub fn slots_until_stale(ctx: Context<SlotsUntilStale>, max_stale_slots: u64) -> Result<i64> {
+        let clock = Clock::get()?;
+        let result_slot = feed_result_slot(&ctx.accounts.feed)?;
+        let remaining = slots_remaining(result_slot, clock.slot, max_stale_slots);
+        msg!("result slot: {}, slots remaining: {}", result_slot, remaining);
+        Ok(remaining)
+    }
+
+    // Require every feed in the remaining accounts to have been sampled at the
+    // same slot, e.g. before combining them into a ratio.
+    pub fn assert_same_slot<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AssertSameSlot>,
+        max_stale_slots: u64,
+    ) -> Result<u64> {
+        let feeds = ctx.remaining_accounts;
+        let expected_slot = common_result_slot(feeds, &Clock::get()?, max_stale_slots)?;
+        msg!("All {} feeds sampled at slot {}", feeds.len(), expected_slot);
+        Ok(expected_slot)
+    }
+
+    // Return numerator / denominator as an 18-decimal fixed point value.
+    pub fn feed_ratio(ctx: Context<FeedRatio>, max_stale_slots: u64) -> Result<i128> {
+        let clock = Clock::get()?;
+        let numerator = feed_value(&ctx.accounts.numerator, &clock, max_stale_slots)?;
+        let denominator = feed_value(&ctx.accounts.denominator, &clock, max_stale_slots)?;
+        let ratio = mul_div(numerator, SCALE, denominator)?;
+        msg!("ratio: {}", ratio);
+        Ok(ratio)
+    }
+
+    pub fn init_twap(ctx: Context<InitTwap>) -> Result<()> {
+        let twap = &mut ctx.accounts.twap;
+        twap.feed = ctx.accounts.feed.key();
+        twap.bump = ctx.bumps.twap;
+        Ok(())
+    }
+
+    // Permissionlessly accumulate the feed into a time-weighted average. Each
+    // price is weighted by the number of slots it was the latest known value.
+    pub fn update_twap(ctx: Context<UpdateTwap>, max_stale_slots: u64) -> Result<()> {
+        let clock = Clock::get()?;
+        let price = feed_value(&ctx.accounts.feed, &clock, max_stale_slots)?;
+        let result_slot = feed_result_slot(&ctx.accounts.feed)?;
+        let twap = &mut ctx.accounts.twap;
+        twap.record(price, result_slot, clock.slot)?;
+        msg!("price: {}, total_slots: {}", price, twap.total_slots);
+        Ok(())
+    }
+
+    // Return the time-weighted average price over all accumulated slots.
+    pub fn get_twap(ctx: Context<GetTwap>) -> Result<i128> {
+        let twap = &ctx.accounts.twap;
+        let average = twap.average()?;
+        msg!("twap: {} over {} slots", average, twap.total_slots);
+        Ok(average)
+    }
+
+    // Read every feed in the remaining accounts, logging the compute units left
+    // before and after so clients can size their compute budget request.
+    pub fn metered_read<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MeteredRead>,
+        max_stale_slots: u64,
+    ) -> Result<()> {
+        let feeds = ctx.remaining_accounts;
+        require!(feeds.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+        anchor_lang::solana_program::log::sol_log_compute_units();
+        let clock = Clock::get()?;
+        for feed in feeds.iter() {
+            let value = feed_value(feed, &clock, max_stale_slots)?;
+            msg!("feed: {}, value: {}", feed.key(), value);
+        }
+        anchor_lang::solana_program::log::sol_log_compute_units();
+        Ok(())
+    }
+
+    // Commit to the values of the feeds in the remaining accounts with a Merkle
+    // root so a light client can later check individual feeds against it.
+    pub fn feed_merkle_root<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FeedMerkleRoot>,
+        max_stale_slots: u64,
+    ) -> Result<[u8; 32]> {
+        let feeds = ctx.remaining_accounts;
+        require!(!feeds.is_empty(), ErrorCode::NoFeedsProvided);
+        require!(feeds.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+        let clock = Clock::get()?;
+        let mut leaves = Vec::with_capacity(feeds.len());
+        for feed in feeds.iter() {
+            let value = feed_value(feed, &clock, max_stale_slots)?;
+            leaves.push(feed_leaf(&feed_hash(feed)?, value, feed_result_slot(feed)?));
+        }
+        let root = merkle_root(leaves);
+        msg!("merkle root: {:?}", root);
+        Ok(root)
+    }
+
+    // Read a feed only if it belongs to one of the approved queues, which lets
+    // integrators fail over between queues without redeploying.
+    pub fn read_from_approved_queue(
+        ctx: Context<ReadFromApprovedQueue>,
+        max_stale_slots: u64,
+    ) -> Result<i128> {
+        check_feed_queue(&ctx.accounts.feed)?;
+        let price = feed_value(&ctx.accounts.feed, &Clock::get()?, max_stale_slots)?;
+        msg!("price: {}", price);
+        Ok(price)
+    }
+
+    pub fn init_price_history(ctx: Context<InitPriceHistory>) -> Result<()> {
+        let history = &mut ctx.accounts.price_history;
+        history.feed = ctx.accounts.feed.key();
+        history.bump = ctx.bumps.price_history;
+        Ok(())
+    }
+
+    // Append the current feed value to the feed's rolling window, evicting the
+    // oldest sample once PRICE_HISTORY_LEN samples are stored.
+    // A feed result is recorded at most once, so repeated calls cannot flush the window.
+    pub fn record_price(ctx: Context<RecordPrice>, max_stale_slots: u64) -> Result<()> {
+        let clock = Clock::get()?;
+        let sample = PriceSample {
+            value: feed_value(&ctx.accounts.feed, &clock, max_stale_slots)?,
+            slot: clock.slot,
+            unix_timestamp: clock.unix_timestamp,
+        };
+        let result_slot = feed_result_slot(&ctx.accounts.feed)?;
+        let history = &mut ctx.accounts.price_history;
+        require!(result_slot > history.last_result_slot, ErrorCode::DuplicateResult);
+        msg!("value: {}, slot: {}, unix_timestamp: {}", sample.value, sample.slot, sample.unix_timestamp);
+        history.push(sample);
+        history.last_result_slot = result_slot;
+        Ok(())
+    }
+
+    // Create an exponential moving average of `feed` with smoothing factor
+    // `alpha_bps` / 10_000.
+    pub fn init_ema(ctx: Context<InitEma>, alpha_bps: u16) -> Result<()> {
+        require!(alpha_bps > 0 && alpha_bps <= 10_000, ErrorCode::InvalidAlpha);
+        let ema = &mut ctx.accounts.ema;
+        ema.feed = ctx.accounts.feed.key();
+        ema.alpha_bps = alpha_bps;
+        ema.bump = ctx.bumps.ema;
+        Ok(())
+    }
+
+    // Fold the current feed value into the EMA: ema += alpha * (price - ema).
+    pub fn update_ema(ctx: Context<UpdateEma>, max_stale_slots: u64) -> Result<i128> {
+        let clock = Clock::get()?;
+        let price = feed_value(&ctx.accounts.feed, &clock, max_stale_slots)?;
+        let result_slot = feed_result_slot(&ctx.accounts.feed)?;
+        let ema = &mut ctx.accounts.ema;
+        ema.record(price, result_slot)?;
+        msg!("price: {}, ema: {}", price, ema.value);
+        Ok(ema.value)
+    }
+
+    // Compute the funding owed by a position of `position_size` (positive for
+    // longs, negative for shorts) from the premium of the mark over the index:
+    // funding = position_size * (mark - index) / index.
+    // A positive result is paid by the position, a negative one is received.
+    pub fn funding_payment(
+        ctx: Context<FundingPayment>,
+        position_size: i64,
+        max_stale_slots: u64,
+    ) -> Result<i128> {
+        let clock = Clock::get()?;
+        let index = feed_value(&ctx.accounts.index_feed, &clock, max_stale_slots)?;
+        let mark = feed_value(&ctx.accounts.mark_feed, &clock, max_stale_slots)?;
+        let spread = mark.checked_sub(index).ok_or(ErrorCode::MathOverflow)?;
+        let premium = mul_div(spread, SCALE, index)?;
+        let payment = mul_div(position_size as i128, premium, SCALE)?;
+        msg!("index: {}, mark: {}, premium: {}, payment: {}", index, mark, premium, payment);
+        Ok(payment)
+    }
+
+    // Use the feed (output tokens per input token) as a guardrail: only forward
+    // `swap_data` to the swap program if the oracle price implies at least
+    // `min_amount_out`. The swap's accounts are passed as remaining accounts.
+    pub fn guarded_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GuardedSwap<'info>>,
+        amount_in: u64,
+        min_amount_out: u64,
+        max_stale_slots: u64,
+        swap_data: Vec<u8>,
+    ) -> Result<()> {
+        let price = feed_value(&ctx.accounts.feed, &Clock::get()?, max_stale_slots)?;
+        let expected_out = check_swap_output(amount_in, price, min_amount_out)?;
+
+        let accounts = ctx
+            .remaining_accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.key(),
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect();
+        let ix = anchor_lang::solana_program::instruction::Instruction {
+            program_id: ctx.accounts.swap_program.key(),
+            accounts,
+            data: swap_data,
+        };
+        let mut account_infos = ctx.remaining_accounts.to_vec();
+        account_infos.push(ctx.accounts.swap_program.to_account_info());
+        anchor_lang::solana_program::program::invoke(&ix, &account_infos)?;
+        msg!("Swap forwarded, oracle expected {} out", expected_out);
+        Ok(())
+    }
+
+    // Log the slots that staleness checks are based on side by side, to help
+    // debug "stale feed" failures.
+    pub fn debug_slots(ctx: Context<DebugSlots>) -> Result<()> {
+        let slots = slot_diagnostics(&ctx.accounts.feed, &Clock::get()?)?;
+        msg!("clock slot: {}", slots.clock_slot);
+        msg!("feed result slot: {}", slots.result_slot);
+        msg!("staleness: {}", slots.staleness);
+        msg!("feed max_staleness: {}", slots.max_staleness);
+        Ok(())
+    }
+
+    // Pin the single feed id this configuration will accept.
+    pub fn init_feed_config(ctx: Context<InitFeedConfig>, expected_feed_id: [u8; 32]) -> Result<()> {
+        let config = &mut ctx.accounts.feed_config;
+        config.authority = ctx.accounts.authority.key();
+        config.expected_feed_id = expected_feed_id;
+        config.bump = ctx.bumps.feed_config;
+        Ok(())
+    }
+
+    // Read the feed only if it is the one pinned in the configuration.
+    pub fn read_configured_feed(ctx: Context<ReadConfiguredFeed>, max_stale_slots: u64) -> Result<i128> {
+        let price = ctx.accounts.feed_config.read(&ctx.accounts.feed, &Clock::get()?, max_stale_slots)?;
+        msg!("price: {}", price);
+        Ok(price)
+    }
+
+    // Log the switchboard-on-demand version this program was built against,
+    // as recorded by build.rs, next to the version the example expects.
+    pub fn version_check(_ctx: Context<VersionCheck>) -> Result<()> {
+        msg!("program version: {}", env!("CARGO_PKG_VERSION"));
+        msg!("switchboard-on-demand linked: {}", env!("SWITCHBOARD_ON_DEMAND_VERSION"));
+        msg!("switchboard-on-demand expected: {}", env!("SWITCHBOARD_ON_DEMAND_EXPECTED_VERSION"));
+        Ok(())
+    }
+
+    pub fn init_feed_stats(ctx: Context<InitFeedStats>) -> Result<()> {
+        let stats = &mut ctx.accounts.feed_stats;
+        stats.feed = ctx.accounts.feed.key();
+        stats.bump = ctx.bumps.feed_stats;
+        Ok(())
+    }
+
+    // Store the latest value together with the spread of the oracle samples it
+    // was computed from, so consumers can widen margins when oracles disagree.
+    pub fn record_feed_stats(ctx: Context<RecordFeedStats>, max_stale_slots: u64) -> Result<()> {
+        let stats = &mut ctx.accounts.feed_stats;
+        stats.record(&ctx.accounts.feed, &Clock::get()?, max_stale_slots)?;
+        msg!("value: {}, std_dev: {}, range: {}", stats.value, stats.std_dev, stats.range);
+        Ok(())
+    }
+
+    pub fn get_feed_stats(ctx: Context<GetFeedStats>) -> Result<FeedStatsView> {
+        let stats = &ctx.accounts.feed_stats;
+        Ok(FeedStatsView {
+            value: stats.value,
+            std_dev: stats.std_dev,
+            range: stats.range,
+            result_slot: stats.result_slot,
+        })
+    }
+
+    pub fn init_feed_names(ctx: Context<InitFeedNames>) -> Result<()> {
+        let names = &mut ctx.accounts.feed_names;
+        names.authority = ctx.accounts.authority.key();
+        names.labels = Vec::new();
+        names.bump = ctx.bumps.feed_names;
+        Ok(())
+    }
+
+    // Label a feed id with a human readable symbol, replacing any existing label.
+    pub fn set_feed_name(ctx: Context<SetFeedName>, feed_id: [u8; 32], symbol: String) -> Result<()> {
+        ctx.accounts.feed_names.set(feed_id, symbol)
+    }
+
+    // Read the feeds in the remaining accounts and return their values labeled
+    // with the registered symbols, or the feed address when unlabeled.
+    pub fn read_labeled<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReadLabeled<'info>>,
+        max_stale_slots: u64,
+    ) -> Result<Vec<LabeledValue>> {
+        let clock = Clock::get()?;
+        let values = ctx.accounts.feed_names.read_labeled(ctx.remaining_accounts, &clock, max_stale_slots)?;
+        for labeled in values.iter() {
+            msg!("{}: {}", labeled.symbol, labeled.value);
+        }
+        Ok(values)
+    }
+
+    // Read the feed only if at least `min_samples` oracle submissions fall
+    // within `max_stale_slots`, a stricter bar than the feed's own minimum.
+    pub fn read_with_min_samples(
+        ctx: Context<ReadWithMinSamples>,
+        min_samples: u32,
+        max_stale_slots: u64,
+    ) -> Result<i128> {
+        let clock = Clock::get()?;
+        let price = value_with_min_samples(&ctx.accounts.feed, &clock, min_samples, max_stale_slots)?;
+        msg!("price: {} from at least {} samples", price, min_samples);
+        Ok(price)
+    }
+
+    // Compute a basket index as the weighted sum of the feeds in the remaining
+    // accounts. Weights are in basis points and must sum to 10_000.
+    pub fn basket_value<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BasketValue>,
+        weights: Vec<BasketWeight>,
+        max_stale_slots: u64,
+    ) -> Result<i128> {
+        let clock = Clock::get()?;
+        let index = basket_index(ctx.remaining_accounts, &weights, &clock, max_stale_slots)?;
+        msg!("basket value: {}", index);
+        Ok(index)
+    }
+
+    // Read the feeds in the remaining accounts, holding each to its own
+    // staleness limit from `max_ages`, or `default_max_stale_slots` if unlisted.
+    pub fn read_with_max_ages<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReadWithMaxAges>,
+        max_ages: Vec<FeedMaxAge>,
+        default_max_stale_slots: u64,
+    ) -> Result<()> {
+        let feeds = ctx.remaining_accounts;
+        let clock = Clock::get()?;
+        let values = values_with_max_ages(feeds, &max_ages, default_max_stale_slots, &clock)?;
+        for (feed, (value, max_stale_slots)) in feeds.iter().zip(values) {
+            msg!("feed: {}, value: {}, max_stale_slots: {}", feed.key(), value, max_stale_slots);
+        }
+        Ok(())
+    }
+
+    // Send the values of the feeds in the remaining accounts to an external event
+    // logger in one CPI. The logger must implement an Anchor instruction
+    // `log_feed_results(results: FeedResults)` taking `logger_state` as its only account.
+    pub fn log_to_event_bus<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LogToEventBus<'info>>,
+        max_stale_slots: u64,
+    ) -> Result<()> {
+        let results = feed_results(ctx.remaining_accounts, &Clock::get()?, max_stale_slots)?;
+        let (logger_program, logger_state) = (&ctx.accounts.logger_program, &ctx.accounts.logger_state);
+        let ix = event_logger_ix(logger_program.key(), logger_state.key(), &results)?;
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[logger_state.to_account_info(), logger_program.to_account_info()],
+        )?;
+        msg!("Logged {} feed results to {}", results.readings.len(), ix.program_id);
+        Ok(())
+    }
+
+    // Read the same asset from two independently sourced feeds and only accept
+    // the result if they agree within `tolerance_bps`. Returns their midpoint.
+    pub fn read_agreed(ctx: Context<ReadAgreed>, tolerance_bps: u16, max_stale_slots: u64) -> Result<i128> {
+        let clock = Clock::get()?;
+        let primary = feed_value(&ctx.accounts.feed, &clock, max_stale_slots)?;
+        let secondary = feed_value(&ctx.accounts.secondary_feed, &clock, max_stale_slots)?;
+        require_agreement(primary, secondary, tolerance_bps)?;
+        let midpoint = primary / 2 + secondary / 2;
+        msg!("primary: {}, secondary: {}, midpoint: {}", primary, secondary, midpoint);
+        Ok(midpoint)
+    }
+
+    pub fn init_compute_model(ctx: Context<InitComputeModel>) -> Result<()> {
+        ctx.accounts.compute_model.bump = ctx.bumps.compute_model;
+        Ok(())
+    }
+
+    // Read the feeds in the remaining accounts, measure the compute units spent
+    // and refit a linear `base + per_feed * feeds` cost model over all samples.
+    pub fn measure_compute<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MeasureCompute<'info>>,
+        max_stale_slots: u64,
+    ) -> Result<()> {
+        use anchor_lang::solana_program::compute_units::sol_remaining_compute_units;
+        let feeds = ctx.remaining_accounts;
+        require!(!feeds.is_empty(), ErrorCode::NoFeedsProvided);
+        require!(feeds.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+        let start = sol_remaining_compute_units();
+        let clock = Clock::get()?;
+        for feed in feeds.iter() {
+            feed_value(feed, &clock, max_stale_slots)?;
+        }
+        let used = start.saturating_sub(sol_remaining_compute_units());
+        msg!("{} feeds used {} compute units ({} per feed)", feeds.len(), used, used / feeds.len() as u64);
+
+        let model = &mut ctx.accounts.compute_model;
+        model.record(feeds.len() as u64, used);
+        msg!("model: {} base + {} per feed", model.base_cu, model.per_feed_cu);
+        Ok(())
+    }
+
+    // Read a feed under an explicit freshness policy instead of a bare
+    // staleness number.
+    pub fn read_with_policy(ctx: Context<ReadWithPolicy>, policy: FreshnessPolicy) -> Result<i128> {
+        let clock = Clock::get()?;
+        let value = feed_value_with_policy(&ctx.accounts.feed, &clock, policy)?;
+        msg!("price: {} ({:?})", value, policy);
+        Ok(value)
+    }
+
+    // Given holdings of two assets in native units, return the trades (in
+    // native units, positive = buy) that bring asset A to `target_a_bps` of
+    // the portfolio value.
+    pub fn rebalance_trades(
+        ctx: Context<RebalanceTrades>,
+        holdings_a: u64,
+        decimals_a: u8,
+        holdings_b: u64,
+        decimals_b: u8,
+        target_a_bps: u16,
+        max_stale_slots: u64,
+    ) -> Result<RebalanceResult> {
+        let clock = Clock::get()?;
+        let price_a = feed_value(&ctx.accounts.feed_a, &clock, max_stale_slots)?;
+        let price_b = feed_value(&ctx.accounts.feed_b, &clock, max_stale_slots)?;
+        let result = rebalance(holdings_a, decimals_a, price_a, holdings_b, decimals_b, price_b, target_a_bps)?;
+        msg!("weight A: {} bps, target: {} bps", result.weight_a_bps, target_a_bps);
+        msg!("trade A: {}, trade B: {}", result.trade_a, result.trade_b);
+        Ok(result)
+    }
+
+    // Median of the feeds in the remaining accounts, leaving out any whose
+    // feed hash is listed in `skip_feeds`. Skipped feeds are not read at all,
+    // so a known-bad feed cannot fail the whole instruction.
+    pub fn aggregate_skipping<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AggregateFeeds>,
+        skip_feeds: Vec<[u8; 32]>,
+        max_stale_slots: u64,
+    ) -> Result<i128> {
+        let feeds = ctx.remaining_accounts;
+        let (median, skipped) = median_skipping(feeds, &skip_feeds, &Clock::get()?, max_stale_slots)?;
+        msg!("median of {} feeds ({} skipped): {}", feeds.len() - skipped, skipped, median);
+        Ok(median)
+    }
+
+    // The freshness precondition lives in the access_control guard, so the
+    // handler only deals with the value itself. The value is still read through
+    // feed_value: the raw `result.value` is not the validated median.
+    #[access_control(verify_fresh_oracle(&ctx.accounts.feed, max_stale_slots))]
+    pub fn guarded_read(ctx: Context<GuardedRead>, max_stale_slots: u64) -> Result<FeedStatsView> {
+        let value = feed_value(&ctx.accounts.feed, &Clock::get()?, max_stale_slots)?;
+        let feed = load_feed(&ctx.accounts.feed)?;
+        msg!("price: {}", value);
+        Ok(FeedStatsView {
+            value,
+            std_dev: feed.result.std_dev,
+            range: feed.result.range,
+            result_slot: feed.result.slot,
+        })
+    }
+
+    // The same guard shared by a second instruction, applied to both feeds.
+    #[access_control(
+        verify_fresh_oracle(&ctx.accounts.numerator, max_stale_slots)
+        verify_fresh_oracle(&ctx.accounts.denominator, max_stale_slots)
+    )]
+    pub fn guarded_ratio(ctx: Context<FeedRatio>, max_stale_slots: u64) -> Result<i128> {
+        let clock = Clock::get()?;
+        let numerator = feed_value(&ctx.accounts.numerator, &clock, max_stale_slots)?;
+        let denominator = feed_value(&ctx.accounts.denominator, &clock, max_stale_slots)?;
+        let ratio = mul_div(numerator, SCALE, denominator)?;
+        msg!("ratio: {}", ratio);
+        Ok(ratio)
+    }
+
+    // Compute the on-chain inputs of an off-chain implied volatility model for
+    // an option struck at `strike` (18 decimals) expiring at `expiry_ts`.
+    // The rate feed is an annualized rate, e.g. 0.05 for 5%.
+    pub fn iv_inputs(
+        ctx: Context<IvInputs>,
+        strike: i128,
+        expiry_ts: i64,
+        max_stale_slots: u64,
+    ) -> Result<IvInputsView> {
+        require!(strike > 0, ErrorCode::InvalidStrike);
+        let clock = Clock::get()?;
+        let seconds_to_expiry = expiry_ts.saturating_sub(clock.unix_timestamp);
+        if seconds_to_expiry <= 0 {
+            msg!("Option expired at {}, now {}", expiry_ts, clock.unix_timestamp);
+            return Err(ErrorCode::OptionExpired.into());
+        }
+        let spot = feed_value(&ctx.accounts.spot_feed, &clock, max_stale_slots)?;
+        let rate = feed_value(&ctx.accounts.rate_feed, &clock, max_stale_slots)?;
+        require!(spot > 0, ErrorCode::InvalidPriceRange);
+
+        let moneyness = mul_div(spot, SCALE, strike)?;
+        let time_to_expiry = mul_div(seconds_to_expiry as i128, SCALE, SECONDS_PER_YEAR)?;
+        let scaled_rate = mul_div(rate, time_to_expiry, SCALE)?;
+        msg!("moneyness: {}, t: {}, r*t: {}", moneyness, time_to_expiry, scaled_rate);
+        Ok(IvInputsView {
+            spot,
+            strike,
+            moneyness,
+            time_to_expiry,
+            scaled_rate,
+        })
+    }
+
+    // Read a feed whose result must have been produced at or after `min_slot`,
+    // in addition to the usual staleness limit. A client sets `min_slot` to the
+    // slot it decided to transact at, so a relayer cannot substitute an older
+    // result that is still within `max_stale_slots`.
+    pub fn read_after_slot(ctx: Context<ReadAfterSlot>, min_slot: u64, max_stale_slots: u64) -> Result<i128> {
+        let value = value_after_slot(&ctx.accounts.feed, &Clock::get()?, min_slot, max_stale_slots)?;
+        msg!("price: {} produced at or after slot {}", value, min_slot);
+        Ok(value)
+    }
+
+    // Report the freshness of the feeds in the remaining accounts for a
+    // monitoring bot. Stale feeds are reported rather than rejected, and only
+    // the result slot of each feed is read to keep the compute cost low.
+    pub fn health<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Health>,
+        max_stale_slots: u64,
+    ) -> Result<HealthStatus> {
+        health_status(ctx.remaining_accounts, &Clock::get()?, max_stale_slots)
+    }
+
+    // Read the feeds in the remaining accounts, requiring their feed hashes to
+    // be exactly `expected_feed_ids`: none missing, none extra, in any order.
+    pub fn read_expected_set<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReadExpectedSet>,
+        expected_feed_ids: Vec<[u8; 32]>,
+        max_stale_slots: u64,
+    ) -> Result<Vec<FeedReading>> {
+        read_expected_feeds(ctx.remaining_accounts, &expected_feed_ids, &Clock::get()?, max_stale_slots)
+    }
+
+    // Read a bid and an ask feed and reject the pair if the bid is above the
+    // ask, which only happens when one of the feeds is reporting bad data.
+    pub fn read_bid_ask(ctx: Context<ReadBidAsk>, max_stale_slots: u64) -> Result<BidAsk> {
+        let clock = Clock::get()?;
+        let (bid_feed, ask_feed) = (&ctx.accounts.bid_feed, &ctx.accounts.ask_feed);
+        let quote = read_bid_ask_pair(bid_feed, ask_feed, &clock, max_stale_slots)?;
+        msg!("bid: {}, ask: {}", quote.bid, quote.ask);
+        Ok(quote)
+    }
+
+    // Read a feed whose result is at most BLOCKHASH_MAX_AGE_SLOTS older than the
+    // current slot, on top of `max_stale_slots`. This is a fixed max-age check
+    // against the Clock: the bound matches the runtime's blockhash age limit,
+    // but it is not derived from this transaction's recent blockhash.
+    pub fn read_within_blockhash_window(ctx: Context<ReadWithinBlockhashWindow>, max_stale_slots: u64) -> Result<i128> {
+        let clock = Clock::get()?;
+        let result_slot = feed_result_slot(&ctx.accounts.feed)?;
+        let horizon = clock.slot.saturating_sub(BLOCKHASH_MAX_AGE_SLOTS);
+        if result_slot < horizon {
+            msg!("Feed result slot {} is older than the max-age horizon {}", result_slot, horizon);
+            return Err(ErrorCode::ResultOlderThanBlockhash.into());
+        }
+        let value = feed_value(&ctx.accounts.feed, &clock, max_stale_slots)?;
+        msg!("price: {} at slot {}", value, result_slot);
+        Ok(value)
+    }
+
+    // Report a stablecoin feed's signed deviation from a 1.0 peg in basis
+    // points (positive for a premium), flagging deviations beyond the threshold.
+    pub fn peg_deviation(ctx: Context<PegDeviation>, depeg_threshold_bps: u16, max_stale_slots: u64) -> Result<PegStatus> {
+        let clock = Clock::get()?;
+        let price = feed_value(&ctx.accounts.feed, &clock, max_stale_slots)?;
+        let status = peg_status(price, depeg_threshold_bps)?;
+        if status.depegged {
+            msg!("DEPEG: price {} is {} bps from peg", price, status.deviation_bps);
+        } else {
+            msg!("price {} is {} bps from peg", price, status.deviation_bps);
+        }
+        Ok(status)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct QueueSummary {
+    pub oracle_count: u32,
+    pub max_quote_verification_age: i64,
+    pub node_timeout: i64,
+    pub oracle_min_stake: u64,
+    pub last_heartbeat: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct FeedReading {
+    pub feed_id: [u8; 32],
+    pub value: i128,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default)]
+pub struct VerificationRecord {
+    pub slot: u64,
+    pub staleness: Option<u64>, // None when the feed could not be loaded
+    pub success: bool,
+}
+
+impl VerificationRecord {
+    /// A verification at `slot` of a feed whose result was produced at
+    /// `result_slot`, or which could not be loaded at all.
+    pub fn new(slot: u64, result_slot: Option<u64>, success: bool) -> Self {
+        let staleness = result_slot.map(|result_slot| slot.saturating_sub(result_slot));
+        Self { slot, staleness, success }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct FeedCallback {
+    pub feed: Pubkey,
+    pub value: i128,
+    pub slot: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default)]
+pub struct PriceSample {
+    pub value: i128,
+    pub slot: u64,
+    pub unix_timestamp: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct FeedStatsView {
+    pub value: i128,
+    pub std_dev: i128,
+    pub range: i128,
+    pub result_slot: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct FeedLabel {
+    pub feed_id: [u8; 32],
+    pub symbol: String,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct LabeledValue {
+    pub symbol: String,
+    pub value: i128,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct BasketWeight {
+    pub feed_id: [u8; 32],
+    pub weight_bps: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct FeedMaxAge {
+    pub feed_id: [u8; 32],
+    pub max_stale_slots: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct FeedResults {
+    pub readings: Vec<FeedReading>,
+    pub slot: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SlotReading {
+    pub value: i128,
+    pub slot: u64, // Slot the read was evaluated at, possibly overridden
+    pub staleness: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RebalanceResult {
+    pub value_a: i128, // 18 decimals, in the feeds' quote currency
+    pub value_b: i128,
+    pub weight_a_bps: u64, // Current share of asset A
+    pub trade_a: i128,     // Native units of A to buy (positive) or sell (negative)
+    pub trade_b: i128,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct IvInputsView {
+    pub spot: i128,
+    pub strike: i128,
+    pub moneyness: i128,      // spot / strike
+    pub time_to_expiry: i128, // In years
+    pub scaled_rate: i128,    // rate * time_to_expiry
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct HealthStatus {
+    pub slot: u64,
+    pub staleness: u64, // Age in slots of the oldest feed result
+    pub feed_count: u8,
+    pub all_fresh: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct BidAsk {
+    pub bid: i128,
+    pub ask: i128,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PegStatus {
+    pub price: i128,
+    pub deviation_bps: i64, // Positive above the peg, negative below
+    pub depegged: bool,
+}
+
+// === Accounts ===
+#[account]
+pub struct VerificationLog {
+    pub entries: [VerificationRecord; VERIFICATION_LOG_LEN],
+    pub next_index: u8, // Slot in `entries` the next record is written to
+    pub count: u8,      // Number of valid entries, saturating at VERIFICATION_LOG_LEN
+    pub bump: u8,
+}
+
+impl VerificationLog {
+    pub const SPACE: usize = 8 + VERIFICATION_LOG_LEN * (8 + 1 + 8 + 1) + 1 + 1 + 1;
+
+    pub fn push(&mut self, record: VerificationRecord) {
+        self.entries[self.next_index as usize] = record;
+        self.next_index = ((self.next_index as usize + 1) % VERIFICATION_LOG_LEN) as u8;
+        if (self.count as usize) < VERIFICATION_LOG_LEN {
+            self.count += 1;
+        }
+    }
+
+    pub fn records(&self) -> Vec<VerificationRecord> {
+        let count = self.count as usize;
+        let start = (self.next_index as usize + VERIFICATION_LOG_LEN - count) % VERIFICATION_LOG_LEN;
+        (0..count)
+            .map(|i| self.entries[(start + i) % VERIFICATION_LOG_LEN])
+            .collect()
+    }
+}
+
+#[account]
+pub struct Twap {
+    pub feed: Pubkey,
+    pub cumulative_price: i128, // sum(price * slots the price was current)
+    pub total_slots: u64,
+    pub last_price: i128,
+    pub last_update_slot: u64,
+    pub last_result_slot: u64, // Feed result slot of the last update, guards double counting
+    pub bump: u8,
+}
+
+impl Twap {
+    /// Fold `price`, produced at `result_slot`, into the accumulator at `slot`.
+    /// The previous price is weighted by the slots since the last update.
+    pub fn record(&mut self, price: i128, result_slot: u64, slot: u64) -> Result<()> {
+        require!(result_slot > self.last_result_slot, ErrorCode::DuplicateResult);
+        if self.last_update_slot != 0 {
+            let elapsed = slot.saturating_sub(self.last_update_slot);
+            let weighted = self
+                .last_price
+                .checked_mul(elapsed as i128)
+                .ok_or(ErrorCode::MathOverflow)?;
+            self.cumulative_price = self
+                .cumulative_price
+                .checked_add(weighted)
+                .ok_or(ErrorCode::MathOverflow)?;
+            self.total_slots = self.total_slots.saturating_add(elapsed);
+        }
+        self.last_price = price;
+        self.last_update_slot = slot;
+        self.last_result_slot = result_slot;
+        Ok(())
+    }
+
+    /// Time-weighted average over all accumulated slots.
+    pub fn average(&self) -> Result<i128> {
+        require!(self.total_slots > 0, ErrorCode::TwapNotReady);
+        Ok(self.cumulative_price / self.total_slots as i128)
+    }
+}
+
+#[account]
+pub struct PriceHistory {
+    pub feed: Pubkey,
+    pub samples: [PriceSample; PRICE_HISTORY_LEN],
+    pub next_index: u8, // Slot in `samples` the next sample is written to
+    pub count: u8,      // Number of valid samples, saturating at PRICE_HISTORY_LEN
+    pub last_result_slot: u64, // Feed result slot of the newest sample
+    pub bump: u8,
+}
+
+impl PriceHistory {
+    pub const SPACE: usize = 8 + 32 + PRICE_HISTORY_LEN * (16 + 8 + 8) + 1 + 1 + 8 + 1;
+
+    pub fn push(&mut self, sample: PriceSample) {
+        self.samples[self.next_index as usize] = sample;
+        self.next_index = ((self.next_index as usize + 1) % PRICE_HISTORY_LEN) as u8;
+        if (self.count as usize) < PRICE_HISTORY_LEN {
+            self.count += 1;
+        }
+    }
+
+    /// Stored samples, oldest first.
+    pub fn samples(&self) -> Vec<PriceSample> {
+        let count = self.count as usize;
+        let start = (self.next_index as usize + PRICE_HISTORY_LEN - count) % PRICE_HISTORY_LEN;
+        (0..count)
+            .map(|i| self.samples[(start + i) % PRICE_HISTORY_LEN])
+            .collect()
+    }
+}
+
+#[account]
+pub struct Ema {
+    pub feed: Pubkey,
+    pub value: i128,
+    pub alpha_bps: u16, // Smoothing factor in basis points
+    pub last_result_slot: u64,
+    pub bump: u8,
+}
+
+impl Ema {
+    /// Fold `price`, produced at `result_slot`, into the average. The first
+    /// price seeds the EMA directly.
+    pub fn record(&mut self, price: i128, result_slot: u64) -> Result<()> {
+        require!(result_slot > self.last_result_slot, ErrorCode::DuplicateResult);
+        self.value = if self.last_result_slot == 0 {
+            price
+        } else {
+            let step = price
+                .checked_sub(self.value)
+                .and_then(|delta| delta.checked_mul(self.alpha_bps as i128))
+                .map(|delta| delta / 10_000)
+                .ok_or(ErrorCode::MathOverflow)?;
+            self.value.checked_add(step).ok_or(ErrorCode::MathOverflow)?
+        };
+        self.last_result_slot = result_slot;
+        Ok(())
+    }
+}
+
+#[account]
+pub struct FeedConfig {
+    pub authority: Pubkey,
+    pub expected_feed_id: [u8; 32], // Feed hash every read must match
+    pub bump: u8,
+}
+
+impl FeedConfig {
+    pub fn read(&self, feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+        if feed_hash(feed)? != self.expected_feed_id {
+            msg!("Feed {} is not the configured feed", feed.key());
+            return Err(ErrorCode::UnexpectedFeed.into());
+        }
+        feed_value(feed, clock, max_stale_slots)
+    }
+}
+
+#[account]
+pub struct FeedStats {
+    pub feed: Pubkey,
+    pub value: i128,
+    pub std_dev: i128, // Standard deviation of the oracle samples, 18 decimals
+    pub range: i128,   // Max minus min oracle sample, 18 decimals
+    pub result_slot: u64,
+    pub bump: u8,
+}
+
+impl FeedStats {
+    // Store the feed's current value and sample spread, leaving the previous
+    // record untouched if the feed is stale.
+    pub fn record(&mut self, feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<()> {
+        let value = feed_value(feed, clock, max_stale_slots)?;
+        let feed = load_feed(feed)?;
+        self.value = value;
+        self.std_dev = feed.result.std_dev;
+        self.range = feed.result.range;
+        self.result_slot = feed.result.slot;
+        Ok(())
+    }
+}
+
+#[account]
+pub struct FeedNames {
+    pub authority: Pubkey,
+    pub labels: Vec<FeedLabel>, // At most MAX_FEED_NAMES entries
+    pub bump: u8,
+}
+
+impl FeedNames {
+    pub const SPACE: usize = 8 + 32 + 4 + MAX_FEED_NAMES * (32 + 4 + MAX_SYMBOL_LEN) + 1;
+
+    // Label a feed id with a symbol, replacing any existing label.
+    pub fn set(&mut self, feed_id: [u8; 32], symbol: String) -> Result<()> {
+        require!(symbol.len() <= MAX_SYMBOL_LEN, ErrorCode::SymbolTooLong);
+        match self.labels.iter_mut().find(|label| label.feed_id == feed_id) {
+            Some(label) => label.symbol = symbol,
+            None => {
+                require!(self.labels.len() < MAX_FEED_NAMES, ErrorCode::TooManyFeeds);
+                self.labels.push(FeedLabel { feed_id, symbol });
+            }
+        }
+        Ok(())
+    }
+
+    // The values of `feeds` labeled with their registered symbols, or with the
+    // feed address when unlabeled.
+    pub fn read_labeled(
+        &self,
+        feeds: &[AccountInfo],
+        clock: &Clock,
+        max_stale_slots: u64,
+    ) -> Result<Vec<LabeledValue>> {
+        require!(feeds.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+        let mut values = Vec::with_capacity(feeds.len());
+        for feed in feeds.iter() {
+            let feed_id = feed_hash(feed)?;
+            let symbol = self
+                .labels
+                .iter()
+                .find(|label| label.feed_id == feed_id)
+                .map(|label| label.symbol.clone())
+                .unwrap_or_else(|| feed.key().to_string());
+            let value = feed_value(feed, clock, max_stale_slots)?;
+            values.push(LabeledValue { symbol, value });
+        }
+        Ok(values)
+    }
+}
+
+#[account]
+pub struct ComputeModel {
+    // Running sums for a least squares fit of compute units against feed count
+    pub samples: u64,
+    pub sum_feeds: u128,
+    pub sum_cu: u128,
+    pub sum_feeds_cu: u128,
+    pub sum_feeds_sq: u128,
+    pub base_cu: u64,
+    pub per_feed_cu: u64,
+    pub bump: u8,
+}
+
+impl ComputeModel {
+    pub fn record(&mut self, feeds: u64, cu: u64) {
+        let (x, y) = (feeds as u128, cu as u128);
+        self.samples += 1;
+        self.sum_feeds += x;
+        self.sum_cu += y;
+        self.sum_feeds_cu += x * y;
+        self.sum_feeds_sq += x * x;
+
+        let n = self.samples as i128;
+        let (sx, sy) = (self.sum_feeds as i128, self.sum_cu as i128);
+        let denominator = n * self.sum_feeds_sq as i128 - sx * sx;
+        if denominator == 0 {
+            // Every sample used the same feed count, so only the average is known
+            self.base_cu = 0;
+            self.per_feed_cu = (sy / sx.max(1)) as u64;
+            return;
+        }
+        let slope = (n * self.sum_feeds_cu as i128 - sx * sy) / denominator;
+        let intercept = (sy - slope * sx) / n;
+        self.per_feed_cu = slope.max(0) as u64;
+        self.base_cu = intercept.max(0) as u64;
+    }
+}
+
+// === Instructions ===
+#[derive(Accounts)]
+pub struct Test<'info> {
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct QueueInfo<'info> {
+    /// CHECK: via switchboard sdk
+    pub queue: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReadWithFallback<'info> {
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    /// CHECK: via switchboard sdk
+    pub secondary_feed: Option<AccountInfo<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct ReadMany {}
+
+#[derive(Accounts)]
+pub struct InitVerificationLog<'info> {
+    #[account(init,
+        payer = payer,
+        seeds = [b"verificationLog".as_ref()],
+        space = VerificationLog::SPACE,
+        bump)]
+    pub verification_log: Account<'info, VerificationLog>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyAndRecord<'info> {
+    #[account(mut, seeds = [b"verificationLog".as_ref()], bump = verification_log.bump)]
+    pub verification_log: Account<'info, VerificationLog>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetVerificationLog<'info> {
+    #[account(seeds = [b"verificationLog".as_ref()], bump = verification_log.bump)]
+    pub verification_log: Account<'info, VerificationLog>,
+}
+
+#[derive(Accounts)]
+pub struct ReadAtSlot<'info> {
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AggregateFeeds {}
+
+#[derive(Accounts)]
+pub struct AssertFeedAt {}
+
+#[derive(Accounts)]
+pub struct StreamFeeds<'info> {
+    /// CHECK: Any program implementing `on_feed_update`.
+    #[account(executable)]
+    pub callback_program: AccountInfo<'info>,
+    /// CHECK: Owned and validated by the callback program.
+    #[account(mut)]
+    pub callback_state: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SlotsUntilStale<'info> {
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AssertSameSlot {}
+
+#[derive(Accounts)]
+pub struct FeedRatio<'info> {
+    /// CHECK: via switchboard sdk
+    pub numerator: AccountInfo<'info>,
+    /// CHECK: via switchboard sdk
+    pub denominator: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitTwap<'info> {
+    #[account(init,
+        payer = payer,
+        seeds = [b"twap".as_ref(), feed.key().as_ref()],
+        space = 8 + 32 + 16 + 8 + 16 + 8 + 8 + 1,
+        bump)]
+    pub twap: Account<'info, Twap>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateTwap<'info> {
+    #[account(mut,
+        seeds = [b"twap".as_ref(), feed.key().as_ref()],
+        bump = twap.bump,
+        has_one = feed)]
+    pub twap: Account<'info, Twap>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetTwap<'info> {
+    pub twap: Account<'info, Twap>,
+}
+
+#[derive(Accounts)]
+pub struct MeteredRead {}
+
+#[derive(Accounts)]
+pub struct FeedMerkleRoot {}
+
+#[derive(Accounts)]
+pub struct ReadFromApprovedQueue<'info> {
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitPriceHistory<'info> {
+    #[account(init,
+        payer = payer,
+        seeds = [b"priceHistory".as_ref(), feed.key().as_ref()],
+        space = PriceHistory::SPACE,
+        bump)]
+    pub price_history: Box<Account<'info, PriceHistory>>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordPrice<'info> {
+    #[account(mut,
+        seeds = [b"priceHistory".as_ref(), feed.key().as_ref()],
+        bump = price_history.bump,
+        has_one = feed)]
+    pub price_history: Box<Account<'info, PriceHistory>>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitEma<'info> {
+    #[account(init,
+        payer = payer,
+        seeds = [b"ema".as_ref(), feed.key().as_ref()],
+        space = 8 + 32 + 16 + 2 + 8 + 1,
+        bump)]
+    pub ema: Account<'info, Ema>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateEma<'info> {
+    #[account(mut,
+        seeds = [b"ema".as_ref(), feed.key().as_ref()],
+        bump = ema.bump,
+        has_one = feed)]
+    pub ema: Account<'info, Ema>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FundingPayment<'info> {
+    /// CHECK: via switchboard sdk
+    pub index_feed: AccountInfo<'info>,
+    /// CHECK: via switchboard sdk
+    pub mark_feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GuardedSwap<'info> {
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    /// CHECK: The swap program validates its own accounts.
+    #[account(executable)]
+    pub swap_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DebugSlots<'info> {
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitFeedConfig<'info> {
+    #[account(init,
+        payer = authority,
+        seeds = [b"feedConfig".as_ref(), authority.key().as_ref()],
+        space = 8 + 32 + 32 + 1,
+        bump)]
+    pub feed_config: Account<'info, FeedConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReadConfiguredFeed<'info> {
+    pub feed_config: Account<'info, FeedConfig>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VersionCheck {}
+
+#[derive(Accounts)]
+pub struct InitFeedStats<'info> {
+    #[account(init,
+        payer = payer,
+        seeds = [b"feedStats".as_ref(), feed.key().as_ref()],
+        space = 8 + 32 + 16 + 16 + 16 + 8 + 1,
+        bump)]
+    pub feed_stats: Account<'info, FeedStats>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordFeedStats<'info> {
+    #[account(mut,
+        seeds = [b"feedStats".as_ref(), feed.key().as_ref()],
+        bump = feed_stats.bump,
+        has_one = feed)]
+    pub feed_stats: Account<'info, FeedStats>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetFeedStats<'info> {
+    pub feed_stats: Account<'info, FeedStats>,
+}
+
+#[derive(Accounts)]
+pub struct InitFeedNames<'info> {
+    #[account(init,
+        payer = authority,
+        seeds = [b"feedNames".as_ref(), authority.key().as_ref()],
+        space = FeedNames::SPACE,
+        bump)]
+    pub feed_names: Account<'info, FeedNames>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeedName<'info> {
+    #[account(mut,
+        seeds = [b"feedNames".as_ref(), authority.key().as_ref()],
+        bump = feed_names.bump,
+        has_one = authority)]
+    pub feed_names: Account<'info, FeedNames>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReadLabeled<'info> {
+    pub feed_names: Account<'info, FeedNames>,
+}
+
+#[derive(Accounts)]
+pub struct ReadWithMinSamples<'info> {
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BasketValue {}
+
+#[derive(Accounts)]
+pub struct ReadWithMaxAges {}
+
+#[derive(Accounts)]
+pub struct LogToEventBus<'info> {
+    /// CHECK: Any program implementing `log_feed_results`.
+    #[account(executable)]
+    pub logger_program: AccountInfo<'info>,
+    /// CHECK: Owned and validated by the logger program.
+    #[account(mut)]
+    pub logger_state: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReadAgreed<'info> {
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    /// CHECK: via switchboard sdk
+    pub secondary_feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitComputeModel<'info> {
+    #[account(init,
+        payer = payer,
+        seeds = [b"computeModel".as_ref()],
+        space = 8 + 8 + 16 * 4 + 8 + 8 + 1,
+        bump)]
+    pub compute_model: Account<'info, ComputeModel>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MeasureCompute<'info> {
+    #[account(mut, seeds = [b"computeModel".as_ref()], bump = compute_model.bump)]
+    pub compute_model: Account<'info, ComputeModel>,
+}
+
+#[derive(Accounts)]
+pub struct ReadWithPolicy<'info> {
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RebalanceTrades<'info> {
+    /// CHECK: via switchboard sdk
+    pub feed_a: AccountInfo<'info>,
+    /// CHECK: via switchboard sdk
+    pub feed_b: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GuardedRead<'info> {
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct IvInputs<'info> {
+    /// CHECK: via switchboard sdk
+    pub spot_feed: AccountInfo<'info>,
+    /// CHECK: via switchboard sdk
+    pub rate_feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReadAfterSlot<'info> {
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Health {}
+
+#[derive(Accounts)]
+pub struct ReadExpectedSet {}
+
+#[derive(Accounts)]
+pub struct ReadBidAsk<'info> {
+    /// CHECK: via switchboard sdk
+    pub bid_feed: AccountInfo<'info>,
+    /// CHECK: via switchboard sdk
+    pub ask_feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReadWithinBlockhashWindow<'info> {
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PegDeviation<'info> {
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+}
+
+// === Errors ===
+#[error_code]
+pub enum ErrorCode {
+    #[msg("The account is not a valid Switchboard queue.")]
+    InvalidQueueAccount,
+    #[msg("The account is not a valid Switchboard pull feed.")]
+    InvalidFeedAccount,
+    #[msg("The feed value is stale.")]
+    StaleFeed,
+    #[msg("Too many feeds requested.")]
+    TooManyFeeds,
+    #[msg("Clock overrides require the clock-override feature.")]
+    ClockOverrideDisabled,
+    #[msg("The feed account is not owned by the Switchboard program.")]
+    InvalidFeedOwner,
+    #[msg("At least one feed must be provided.")]
+    NoFeedsProvided,
+    #[msg("The same feed was provided more than once.")]
+    DuplicateFeed,
+    #[msg("Arithmetic overflow.")]
+    MathOverflow,
+    #[msg("One decimals entry is required per feed.")]
+    DecimalsMismatch,
+    #[msg("No feed was supplied at the requested index.")]
+    FeedIndexOutOfRange,
+    #[msg("The feed does not match the expected feed id.")]
+    UnexpectedFeed,
+    #[msg("The feed result is from a slot ahead of the current clock.")]
+    FeedFromFutureSlot,
+    #[msg("The feeds were not all sampled at the same slot.")]
+    InconsistentSlots,
+    #[msg("Division by zero.")]
+    DivideByZero,
+    #[msg("This feed result has already been recorded.")]
+    DuplicateResult,
+    #[msg("Not enough updates have been recorded yet.")]
+    TwapNotReady,
+    #[msg("The feed's queue is not on the approved list.")]
+    UnapprovedQueue,
+    #[msg("Alpha must be between 1 and 10000 basis points.")]
+    InvalidAlpha,
+    #[msg("The oracle price implies less output than the minimum accepted.")]
+    OracleGuardrail,
+    #[msg("Symbols are limited to 16 bytes.")]
+    SymbolTooLong,
+    #[msg("Not enough fresh oracle samples.")]
+    InsufficientSignatures,
+    #[msg("Weights must sum to 10000 basis points.")]
+    InvalidWeights,
+    #[msg("A referenced feed was not supplied.")]
+    MissingFeed,
+    #[msg("The price range is invalid.")]
+    InvalidPriceRange,
+    #[msg("The two price sources disagree beyond the tolerance.")]
+    SourcesDisagree,
+    #[msg("The option strike must be positive.")]
+    InvalidStrike,
+    #[msg("The option has already expired.")]
+    OptionExpired,
+    #[msg("The feed result was produced before the requested minimum slot.")]
+    ResultBeforeMinSlot,
+    #[msg("The supplied feeds do not match the expected feed set.")]
+    UnexpectedFeeds,
+    #[msg("The bid price is above the ask price.")]
+    InvariantViolated,
+    #[msg("The feed result is older than BLOCKHASH_MAX_AGE_SLOTS.")]
+    ResultOlderThanBlockhash,
+    #[msg("The queue account is not owned by the Switchboard program.")]
+    InvalidQueueOwner,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(whole: i128) -> i128 {
+        whole * SCALE
+    }
+
+    // Slot of the test clock; fixture results are produced a few slots before it.
+    const NOW: u64 = 1_000;
+
+    fn clock_at(slot: u64) -> Clock {
+        Clock { slot, ..Clock::default() }
+    }
+
+    /// Backing storage for a Switchboard pull feed account whose only oracle
+    /// reported `value` at `slot`, owned by the on-demand program.
+    struct TestFeed {
+        key: Pubkey,
+        lamports: u64,
+        buf: Vec<u8>,
+        start: usize,
+    }
+
+    impl TestFeed {
+        fn new(value: i128, slot: u64) -> Self {
+            Self::with(value, slot, |_| {})
+        }
+
+        /// Like `new`, letting `edit` adjust the account before it is written.
+        fn with(value: i128, slot: u64, edit: impl FnOnce(&mut PullFeedAccountData)) -> Self {
+            // SAFETY: the account is a zero-copy plain-old-data struct, for
+            // which all zero bytes are a valid value.
+            let mut feed: PullFeedAccountData = unsafe { std::mem::zeroed() };
+            feed.result.value = value;
+            feed.result.slot = slot;
+            feed.submissions[0].oracle = Pubkey::new_unique();
+            feed.submissions[0].slot = slot;
+            feed.submissions[0].value = value;
+            edit(&mut feed);
+            // SAFETY: as above, the struct has no padding-sensitive invariants.
+            let bytes = unsafe {
+                std::slice::from_raw_parts(
+                    &feed as *const PullFeedAccountData as *const u8,
+                    std::mem::size_of::<PullFeedAccountData>(),
+                )
+            };
+            // The SDK casts the bytes after the discriminator in place, so they
+            // must be 16-byte aligned for the i128 fields.
+            let mut buf = vec![0u8; 8 + bytes.len() + 16];
+            let start = (16 - (buf.as_ptr() as usize + 8) % 16) % 16;
+            let discriminator = anchor_lang::solana_program::hash::hash(b"account:PullFeedAccountData");
+            buf[start..start + 8].copy_from_slice(&discriminator.to_bytes()[..8]);
+            buf[start + 8..start + 8 + bytes.len()].copy_from_slice(bytes);
+            Self { key: Pubkey::new_unique(), lamports: 1, buf, start }
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            let len = 8 + std::mem::size_of::<PullFeedAccountData>();
+            let data = &mut self.buf[self.start..self.start + len];
+            AccountInfo::new(&self.key, false, false, &mut self.lamports, data, &ON_DEMAND_MAINNET_PID, false, 0)
+        }
+    }
+
+    #[test]
+    fn test_feed_reads_back_through_feed_value() {
+        let mut feed = TestFeed::new(price(42), NOW - 5);
+        let info = feed.info();
+        assert_eq!(feed_value(&info, &clock_at(NOW), 10).unwrap(), price(42));
+        assert_eq!(feed_value(&info, &clock_at(NOW), 2).unwrap_err(), Error::from(ErrorCode::StaleFeed));
+        assert_eq!(feed_result_slot(&info).unwrap(), NOW - 5);
+    }
+
+    #[test]
+    fn result_ahead_of_the_clock_is_not_reported_as_stale() {
+        // A local validator whose clock was never advanced still reports an
+        // early slot while the update carries a real one.
+        let mut feed = TestFeed::new(price(42), NOW + 5);
+        let error = feed_value(&feed.info(), &clock_at(NOW), 10).unwrap_err();
+        assert_eq!(error, Error::from(ErrorCode::FeedFromFutureSlot));
+        assert_ne!(error, Error::from(ErrorCode::StaleFeed));
+        // At the result's own slot it reads normally.
+        assert_eq!(feed_value(&feed.info(), &clock_at(NOW + 5), 10).unwrap(), price(42));
+    }
+
+    #[test]
+    fn fallback_is_used_only_when_the_primary_is_stale() {
+        let mut fresh = TestFeed::new(price(100), NOW - 1);
+        let mut stale = TestFeed::new(price(90), NOW - 50);
+        let mut secondary = TestFeed::new(price(101), NOW - 2);
+        let (fresh, stale, secondary) = (fresh.info(), stale.info(), secondary.info());
+        let clock = clock_at(NOW);
+
+        // Primary fresh: the secondary is ignored.
+        assert_eq!(
+            value_with_fallback(&fresh, Some(&secondary), &clock, 10).unwrap(),
+            (price(100), PriceSource::Primary)
+        );
+        // Primary stale: the secondary answers.
+        assert_eq!(
+            value_with_fallback(&stale, Some(&secondary), &clock, 10).unwrap(),
+            (price(101), PriceSource::Secondary)
+        );
+        // Primary stale and no fresh secondary: still stale.
+        let stale_error = Error::from(ErrorCode::StaleFeed);
+        assert_eq!(value_with_fallback(&stale, None, &clock, 10).unwrap_err(), stale_error);
+        assert_eq!(value_with_fallback(&stale, Some(&stale), &clock, 10).unwrap_err(), stale_error);
+    }
+
+    #[test]
+    fn clock_override_requires_the_feature() {
+        assert_eq!(override_clock(clock_at(NOW), None).unwrap().slot, NOW);
+        let overridden = override_clock(clock_at(NOW), Some(NOW - 40));
+        if cfg!(feature = "clock-override") {
+            // A result 50 slots old is fresh when evaluated 40 slots back.
+            let clock = overridden.unwrap();
+            let mut feed = TestFeed::new(price(7), NOW - 50);
+            assert_eq!(feed_value(&feed.info(), &clock, 10).unwrap(), price(7));
+        } else {
+            assert_eq!(overridden.unwrap_err(), Error::from(ErrorCode::ClockOverrideDisabled));
+        }
+    }
+
+    #[test]
+    fn replayed_reading_reports_staleness_at_the_historical_slot() {
+        // A result from slot 500, long stale at NOW, replayed as of slot 504.
+        let mut feed = TestFeed::new(price(42), 500);
+        let info = feed.info();
+        let reading = slot_reading(&info, &clock_at(504), 10).unwrap();
+        assert_eq!((reading.value, reading.slot, reading.staleness), (price(42), 504, 4));
+        assert_eq!(slot_reading(&info, &clock_at(NOW), 10).unwrap_err(), Error::from(ErrorCode::StaleFeed));
+        // Replayed past the limit, it is stale again.
+        assert_eq!(slot_reading(&info, &clock_at(511), 10).unwrap_err(), Error::from(ErrorCode::StaleFeed));
+
+        if cfg!(feature = "clock-override") {
+            let clock = override_clock(clock_at(NOW), Some(504)).unwrap();
+            assert_eq!(slot_reading(&info, &clock, 10).unwrap().staleness, 4);
+        }
+    }
+
+    #[test]
+    fn median_of_a_variable_number_of_feeds() {
+        let owner = Pubkey::new_unique();
+        let mut feeds: Vec<TestFeed> = [5, 1, 4, 2, 3].iter().map(|&v| TestFeed::new(price(v), NOW)).collect();
+        let infos: Vec<AccountInfo> = feeds.iter_mut().map(|feed| feed.info()).collect();
+        let clock = clock_at(NOW);
+        // Upper median of however many feeds are passed.
+        assert_eq!(median_of_feeds(&infos[..1], &clock, 10).unwrap(), price(5));
+        assert_eq!(median_of_feeds(&infos[..2], &clock, 10).unwrap(), price(5));
+        assert_eq!(median_of_feeds(&infos[..3], &clock, 10).unwrap(), price(4));
+        assert_eq!(median_of_feeds(&infos, &clock, 10).unwrap(), price(3));
+
+        assert_eq!(median_of_feeds(&[], &clock, 10).unwrap_err(), Error::from(ErrorCode::NoFeedsProvided));
+        let repeated = [infos[0].clone(), infos[1].clone(), infos[0].clone()];
+        assert_eq!(median_of_feeds(&repeated, &clock, 10).unwrap_err(), Error::from(ErrorCode::DuplicateFeed));
+        let mut forged = infos[2].clone();
+        forged.owner = &owner;
+        let with_forged = [infos[0].clone(), forged];
+        assert_eq!(median_of_feeds(&with_forged, &clock, 10).unwrap_err(), Error::from(ErrorCode::InvalidFeedOwner));
+    }
+
+    #[test]
+    fn slots_remaining_for_known_results() {
+        // Produced 10 slots ago with a 25 slot budget: 15 to go.
+        assert_eq!(slots_remaining(NOW - 10, NOW, 25), 15);
+        assert_eq!(slots_remaining(NOW - 25, NOW, 25), 0);
+        // Already 5 slots past the budget.
+        assert_eq!(slots_remaining(NOW - 30, NOW, 25), -5);
+        // Extremes clamp instead of wrapping.
+        assert_eq!(slots_remaining(NOW, NOW, u64::MAX), i64::MAX);
+        assert_eq!(slots_remaining(0, u64::MAX, 0), i64::MIN);
+    }
+
+    #[test]
+    fn feeds_must_share_their_result_slot() {
+        let mut a = TestFeed::new(price(60_000), NOW - 3);
+        let mut b = TestFeed::new(price(3_000), NOW - 3);
+        let mut c = TestFeed::new(price(150), NOW - 2);
+        let (a, b, c) = (a.info(), b.info(), c.info());
+        let clock = clock_at(NOW);
+        assert_eq!(common_result_slot(&[a.clone(), b.clone()], &clock, 10).unwrap(), NOW - 3);
+        assert_eq!(
+            common_result_slot(&[a, b, c], &clock, 10).unwrap_err(),
+            Error::from(ErrorCode::InconsistentSlots)
+        );
+        assert_eq!(common_result_slot(&[], &clock, 10).unwrap_err(), Error::from(ErrorCode::NoFeedsProvided));
+    }
+
+    #[test]
+    fn only_feeds_of_approved_queues_are_read() {
+        for queue in ALLOWED_QUEUES {
+            let mut feed = TestFeed::with(price(1), NOW, |feed| feed.queue = *queue);
+            check_feed_queue(&feed.info()).unwrap();
+        }
+        let mut feed = TestFeed::with(price(1), NOW, |feed| feed.queue = Pubkey::new_unique());
+        assert_eq!(check_feed_queue(&feed.info()).unwrap_err(), Error::from(ErrorCode::UnapprovedQueue));
+    }
+
+    #[test]
+    fn debug_slots_values_are_consistent() {
+        let mut feed = TestFeed::with(price(1), NOW - 7, |feed| feed.max_staleness = 25);
+        let info = feed.info();
+        let slots = slot_diagnostics(&info, &clock_at(NOW)).unwrap();
+        assert_eq!(
+            slots,
+            SlotDiagnostics { clock_slot: NOW, result_slot: NOW - 7, staleness: 7, max_staleness: 25 }
+        );
+        assert_eq!(slots.staleness, slots.clock_slot as i128 - slots.result_slot as i128);
+        // The logged staleness is the one feed_value enforces.
+        feed_value(&info, &clock_at(NOW), 7).unwrap();
+        assert!(feed_value(&info, &clock_at(NOW), 6).is_err());
+        // A clock behind the result shows up as negative staleness.
+        assert_eq!(slot_diagnostics(&info, &clock_at(NOW - 10)).unwrap().staleness, -3);
+    }
+
+    #[test]
+    fn feed_stats_record_the_sample_spread() {
+        let mut feed = TestFeed::with(price(100), NOW - 2, |feed| {
+            feed.result.std_dev = price(1) / 2;
+            feed.result.range = price(2);
+        });
+        let info = feed.info();
+        let mut stats = FeedStats {
+            feed: *info.key,
+            value: 0,
+            std_dev: 0,
+            range: 0,
+            result_slot: 0,
+            bump: 255,
+        };
+        stats.record(&info, &clock_at(NOW), 10).unwrap();
+        assert_eq!(
+            (stats.value, stats.std_dev, stats.range, stats.result_slot),
+            (price(100), price(1) / 2, price(2), NOW - 2)
+        );
+
+        // A stale feed does not overwrite the last good record.
+        let mut stale = TestFeed::with(price(90), NOW - 50, |feed| feed.result.std_dev = price(9));
+        assert_eq!(
+            stats.record(&stale.info(), &clock_at(NOW), 10).unwrap_err(),
+            Error::from(ErrorCode::StaleFeed)
+        );
+        assert_eq!((stats.value, stats.std_dev), (price(100), price(1) / 2));
+    }
+
+    #[test]
+    fn min_samples_threshold_rejects_too_few_signatures() {
+        // Two fresh submissions and one older than the staleness window.
+        let mut feed = TestFeed::with(price(100), NOW, |feed| {
+            for (i, slot) in [(1, NOW - 1), (2, NOW - 40)] {
+                feed.submissions[i].oracle = Pubkey::new_unique();
+                feed.submissions[i].slot = slot;
+                feed.submissions[i].value = price(100);
+            }
+        });
+        let info = feed.info();
+        let clock = clock_at(NOW);
+        assert_eq!(value_with_min_samples(&info, &clock, 2, 10).unwrap(), price(100));
+        assert_eq!(
+            value_with_min_samples(&info, &clock, 3, 10).unwrap_err(),
+            Error::from(ErrorCode::InsufficientSignatures)
+        );
+        // Widening the window lets the third submission count.
+        assert_eq!(value_with_min_samples(&info, &clock, 3, 50).unwrap(), price(100));
+    }
+
+    #[test]
+    fn basket_index_of_two_feeds() {
+        let (btc, eth) = ([1u8; 32], [2u8; 32]);
+        let mut btc_feed = feed_with_id(btc, price(60_000));
+        let mut eth_feed = feed_with_id(eth, price(3_000));
+        let feeds = [btc_feed.info(), eth_feed.info()];
+        let clock = clock_at(NOW);
+        let weight = |feed_id, weight_bps| BasketWeight { feed_id, weight_bps };
+        // 60% BTC at 60000 plus 40% ETH at 3000.
+        let weights = [weight(btc, 6_000), weight(eth, 4_000)];
+        assert_eq!(basket_index(&feeds, &weights, &clock, 10).unwrap(), price(37_200));
+
+        let short = [weight(btc, 6_000), weight(eth, 3_000)];
+        assert_eq!(
+            basket_index(&feeds, &short, &clock, 10).unwrap_err(),
+            Error::from(ErrorCode::InvalidWeights)
+        );
+        let overflowing = [weight(btc, u64::MAX), weight(eth, 1)];
+        assert_eq!(
+            basket_index(&feeds, &overflowing, &clock, 10).unwrap_err(),
+            Error::from(ErrorCode::InvalidWeights)
+        );
+        let unknown = [weight(btc, 6_000), weight([3u8; 32], 4_000)];
+        assert_eq!(
+            basket_index(&feeds, &unknown, &clock, 10).unwrap_err(),
+            Error::from(ErrorCode::MissingFeed)
+        );
+    }
+
+    #[test]
+    fn each_feed_is_held_to_its_own_max_age() {
+        let (slow, fast) = ([1u8; 32], [2u8; 32]);
+        let mut slow_feed = TestFeed::with(price(1), NOW - 50, |feed| feed.feed_hash = slow);
+        let mut fast_feed = TestFeed::with(price(2), NOW - 5, |feed| feed.feed_hash = fast);
+        let feeds = [slow_feed.info(), fast_feed.info()];
+        // The slow feed has its own 100 slot limit, the fast one falls back to 10.
+        let max_ages = [FeedMaxAge { feed_id: slow, max_stale_slots: 100 }];
+        assert_eq!(
+            values_with_max_ages(&feeds, &max_ages, 10, &clock_at(NOW)).unwrap(),
+            vec![(price(1), 100), (price(2), 10)]
+        );
+        // At the same ages the slow feed alone fails the default limit...
+        assert_eq!(
+            values_with_max_ages(&feeds, &[], 10, &clock_at(NOW)).unwrap_err(),
+            Error::from(ErrorCode::StaleFeed)
+        );
+        // ...and 20 slots later only the fast feed is too old.
+        assert_eq!(
+            values_with_max_ages(&feeds[1..], &max_ages, 10, &clock_at(NOW + 20)).unwrap_err(),
+            Error::from(ErrorCode::StaleFeed)
+        );
+        assert!(values_with_max_ages(&feeds[..1], &max_ages, 10, &clock_at(NOW + 20)).is_ok());
+    }
+
+    #[test]
+    fn compute_model_fits_per_feed_cost_over_1_2_and_4_feeds() {
+        let mut model = ComputeModel {
+            samples: 0,
+            sum_feeds: 0,
+            sum_cu: 0,
+            sum_feeds_cu: 0,
+            sum_feeds_sq: 0,
+            base_cu: 0,
+            per_feed_cu: 0,
+            bump: 255,
+        };
+        // A single feed count only yields the average.
+        model.record(1, 8_000);
+        assert_eq!((model.base_cu, model.per_feed_cu), (0, 8_000));
+        // Roughly 5000 base plus 3000 per feed, with some noise.
+        model.record(2, 11_100);
+        model.record(4, 16_900);
+        assert!(model.per_feed_cu.abs_diff(3_000) <= 100, "per feed: {}", model.per_feed_cu);
+        assert!(model.base_cu.abs_diff(5_000) <= 200, "base: {}", model.base_cu);
+        for feeds in [1, 2, 4] {
+            let predicted = model.base_cu + model.per_feed_cu * feeds;
+            assert!(predicted.abs_diff(5_000 + 3_000 * feeds) <= 300);
+        }
+    }
+
+    #[test]
+    fn freshness_policies_at_various_slot_deltas() {
+        let mut feed = TestFeed::with(price(3), NOW, |feed| feed.max_staleness = 25);
+        let info = feed.info();
+        let read = |delta: u64, policy| feed_value_with_policy(&info, &clock_at(NOW + delta), policy);
+        let stale = || Error::from(ErrorCode::StaleFeed);
+
+        for (delta, fresh) in [(0, true), (10, true), (11, false)] {
+            assert_eq!(read(delta, FreshnessPolicy::Strict(10)).is_ok(), fresh, "Strict at +{}", delta);
+        }
+        assert_eq!(read(11, FreshnessPolicy::Strict(10)).unwrap_err(), stale());
+        // BestEffort follows the feed's own 25 slot max_staleness.
+        assert_eq!(read(25, FreshnessPolicy::BestEffort).unwrap(), price(3));
+        assert_eq!(read(26, FreshnessPolicy::BestEffort).unwrap_err(), stale());
+        assert_eq!(read(0, FreshnessPolicy::RequireFresh).unwrap(), price(3));
+        assert_eq!(read(1, FreshnessPolicy::RequireFresh).unwrap_err(), stale());
+    }
+
+    #[test]
+    fn rebalance_trades_for_a_skewed_portfolio() {
+        // 8 SOL at 100 and 200 USDC at 1: 80% SOL against a 50% target.
+        let sol = 8 * 10u64.pow(9);
+        let usdc = 200 * 10u64.pow(6);
+        let result = rebalance(sol, 9, price(100), usdc, 6, price(1), 5_000).unwrap();
+        assert_eq!((result.value_a, result.value_b), (price(800), price(200)));
+        assert_eq!(result.weight_a_bps, 8_000);
+        // Sell 3 SOL for 300 USDC.
+        assert_eq!(result.trade_a, -3 * 10i128.pow(9));
+        assert_eq!(result.trade_b, 300 * 10i128.pow(6));
+
+        // Already on target: nothing to trade.
+        let balanced = rebalance(sol, 9, price(100), usdc, 6, price(1), 8_000).unwrap();
+        assert_eq!((balanced.trade_a, balanced.trade_b), (0, 0));
+        assert_eq!(
+            rebalance(sol, 9, price(100), usdc, 6, price(1), 10_001).unwrap_err(),
+            Error::from(ErrorCode::InvalidWeights)
+        );
+        assert_eq!(
+            rebalance(sol, 9, 0, usdc, 6, price(1), 5_000).unwrap_err(),
+            Error::from(ErrorCode::DivideByZero)
+        );
+    }
+
+    #[test]
+    fn skipped_feed_is_left_out_of_the_median() {
+        let (btc_a, btc_b, btc_c, bad) = ([1u8; 32], [2u8; 32], [3u8; 32], [4u8; 32]);
+        let mut a = feed_with_id(btc_a, price(60_000));
+        let mut b = feed_with_id(btc_b, price(60_100));
+        let mut c = feed_with_id(btc_c, price(60_200));
+        // Stale and far off: would fail the read if it were not skipped.
+        let mut broken = TestFeed::with(price(1), NOW - 100, |feed| feed.feed_hash = bad);
+        let feeds = [a.info(), broken.info(), b.info(), c.info()];
+        let clock = clock_at(NOW);
+        assert_eq!(median_skipping(&feeds, &[bad], &clock, 10).unwrap(), (price(60_100), 1));
+        assert_eq!(median_skipping(&feeds, &[], &clock, 10).unwrap_err(), Error::from(ErrorCode::StaleFeed));
+        assert_eq!(
+            median_skipping(&feeds[..2], &[btc_a, bad], &clock, 10).unwrap_err(),
+            Error::from(ErrorCode::NoFeedsProvided)
+        );
+    }
+
+    #[test]
+    fn results_before_min_slot_are_rejected_even_when_fresh() {
+        let mut feed = TestFeed::new(price(5), NOW - 3);
+        let info = feed.info();
+        let clock = clock_at(NOW);
+        assert_eq!(value_after_slot(&info, &clock, NOW - 4, 10).unwrap(), price(5));
+        assert_eq!(value_after_slot(&info, &clock, NOW - 3, 10).unwrap(), price(5));
+        // One slot past the result, though it is well within max_stale_slots.
+        assert_eq!(
+            value_after_slot(&info, &clock, NOW - 2, 10).unwrap_err(),
+            Error::from(ErrorCode::ResultBeforeMinSlot)
+        );
+    }
+
+    #[test]
+    fn health_reports_the_stalest_of_several_feeds() {
+        let mut fresh = TestFeed::new(price(1), NOW - 2);
+        let mut lagging = TestFeed::new(price(2), NOW - 25);
+        let mut recent = TestFeed::new(price(3), NOW);
+        let feeds = [fresh.info(), lagging.info(), recent.info()];
+        let clock = clock_at(NOW);
+
+        let status = health_status(&feeds, &clock, 10).unwrap();
+        assert_eq!(
+            (status.slot, status.staleness, status.feed_count, status.all_fresh),
+            (NOW, 25, 3, false)
+        );
+        // Without the lagging feed the set is healthy.
+        let status = health_status(&[feeds[0].clone(), feeds[2].clone()], &clock, 10).unwrap();
+        assert_eq!((status.staleness, status.feed_count, status.all_fresh), (2, 2, true));
+        assert_eq!(health_status(&[], &clock, 10).unwrap_err(), Error::from(ErrorCode::NoFeedsProvided));
+    }
+
+    #[test]
+    fn bundle_must_hold_exactly_the_expected_feeds() {
+        let (btc, eth, sol) = ([1u8; 32], [2u8; 32], [3u8; 32]);
+        let mut btc_feed = feed_with_id(btc, price(60_000));
+        let mut eth_feed = feed_with_id(eth, price(3_000));
+        let mut sol_feed = feed_with_id(sol, price(150));
+        let feeds = [eth_feed.info(), btc_feed.info(), sol_feed.info()];
+        let clock = clock_at(NOW);
+        let unexpected = || Error::from(ErrorCode::UnexpectedFeeds);
+
+        // Matching, in a different order than expected.
+        let readings = read_expected_feeds(&feeds[..2], &[btc, eth], &clock, 10).unwrap();
+        let pairs: Vec<([u8; 32], i128)> = readings.iter().map(|r| (r.feed_id, r.value)).collect();
+        assert_eq!(pairs, vec![(eth, price(3_000)), (btc, price(60_000))]);
+        // An extra feed, a missing feed, and one substituted for another.
+        assert_eq!(read_expected_feeds(&feeds, &[btc, eth], &clock, 10).unwrap_err(), unexpected());
+        assert_eq!(read_expected_feeds(&feeds[..1], &[btc, eth], &clock, 10).unwrap_err(), unexpected());
+        assert_eq!(read_expected_feeds(&feeds[1..], &[btc, eth], &clock, 10).unwrap_err(), unexpected());
+        // A repeated feed cannot stand in for an expected one.
+        let repeated = [feeds[1].clone(), feeds[1].clone()];
+        assert_eq!(
+            read_expected_feeds(&repeated, &[btc, btc], &clock, 10).unwrap_err(),
+            Error::from(ErrorCode::DuplicateFeed)
+        );
+    }
+
+    #[test]
+    fn inverted_bid_ask_violates_the_invariant() {
+        let mut bid = TestFeed::new(price(99), NOW);
+        let mut ask = TestFeed::new(price(101), NOW);
+        let (bid, ask) = (bid.info(), ask.info());
+        let clock = clock_at(NOW);
+        let quote = read_bid_ask_pair(&bid, &ask, &clock, 10).unwrap();
+        assert_eq!((quote.bid, quote.ask), (price(99), price(101)));
+        // A locked market is still valid.
+        assert!(read_bid_ask_pair(&bid, &bid, &clock, 10).is_ok());
+        assert_eq!(
+            read_bid_ask_pair(&ask, &bid, &clock, 10).unwrap_err(),
+            Error::from(ErrorCode::InvariantViolated)
+        );
+    }
+
+    #[test]
+    fn peg_deviation_on_peg_at_a_premium_and_depegged() {
+        let bps = |bps: i128| SCALE + SCALE * bps / 10_000;
+        let status = |price| {
+            let status = peg_status(price, 50).unwrap();
+            (status.deviation_bps, status.depegged)
+        };
+        assert_eq!(status(SCALE), (0, false));
+        assert_eq!(status(bps(25)), (25, false));
+        // At the threshold is still on peg; past it in either direction is not.
+        assert_eq!(status(bps(50)), (50, false));
+        assert_eq!(status(bps(51)), (51, true));
+        assert_eq!(status(bps(-120)), (-120, true));
+        assert_eq!(peg_status(i128::MAX, 50).unwrap_err(), Error::from(ErrorCode::MathOverflow));
+    }
+
+    /// A fresh feed whose job definitions hash to `feed_id`.
+    fn feed_with_id(feed_id: [u8; 32], value: i128) -> TestFeed {
+        TestFeed::with(value, NOW, |feed| feed.feed_hash = feed_id)
+    }
+
+    #[test]
+    fn feed_config_reads_only_the_pinned_feed() {
+        let (btc, eth) = ([1u8; 32], [2u8; 32]);
+        let config = FeedConfig { authority: Pubkey::new_unique(), expected_feed_id: btc, bump: 255 };
+        let mut pinned = feed_with_id(btc, price(60_000));
+        let mut swapped = feed_with_id(eth, price(3_000));
+        assert_eq!(config.read(&pinned.info(), &clock_at(NOW), 10).unwrap(), price(60_000));
+        assert_eq!(
+            config.read(&swapped.info(), &clock_at(NOW), 10).unwrap_err(),
+            Error::from(ErrorCode::UnexpectedFeed)
+        );
+    }
+
+    #[test]
+    fn feed_names_label_read_values() {
+        let (btc, eth, sol) = ([1u8; 32], [2u8; 32], [3u8; 32]);
+        let mut names = FeedNames { authority: Pubkey::new_unique(), labels: Vec::new(), bump: 255 };
+        names.set(btc, "BTC".to_string()).unwrap();
+        names.set(eth, "ETH?".to_string()).unwrap();
+        names.set(eth, "ETH".to_string()).unwrap();
+        assert_eq!(
+            names.set(sol, "S".repeat(MAX_SYMBOL_LEN + 1)).unwrap_err(),
+            Error::from(ErrorCode::SymbolTooLong)
+        );
+        assert_eq!(names.labels.len(), 2);
+
+        let mut btc_feed = feed_with_id(btc, price(60_000));
+        let mut eth_feed = feed_with_id(eth, price(3_000));
+        let mut sol_feed = feed_with_id(sol, price(150));
+        let feeds = [eth_feed.info(), sol_feed.info(), btc_feed.info()];
+        let labeled: Vec<(String, i128)> = names
+            .read_labeled(&feeds, &clock_at(NOW), 10)
+            .unwrap()
+            .into_iter()
+            .map(|labeled| (labeled.symbol, labeled.value))
+            .collect();
+        assert_eq!(
+            labeled,
+            vec![
+                ("ETH".to_string(), price(3_000)),
+                (feeds[1].key.to_string(), price(150)),
+                ("BTC".to_string(), price(60_000)),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_many_returns_requested_ids_in_order_with_zero_for_absent() {
+        let (btc, eth, absent) = ([1u8; 32], [2u8; 32], [3u8; 32]);
+        let mut btc_feed = feed_with_id(btc, price(60_000));
+        let mut eth_feed = feed_with_id(eth, price(3_000));
+        let feeds = [btc_feed.info(), eth_feed.info()];
+        let readings = read_feeds_by_id(&feeds, vec![eth, absent, btc, eth], &clock_at(NOW), 10).unwrap();
+        let pairs: Vec<([u8; 32], i128)> = readings.iter().map(|r| (r.feed_id, r.value)).collect();
+        assert_eq!(pairs, vec![(eth, price(3_000)), (absent, 0), (btc, price(60_000)), (eth, price(3_000))]);
+    }
+
+    #[test]
+    fn assert_feed_at_matching_mismatching_and_out_of_range() {
+        let (btc, eth) = ([1u8; 32], [2u8; 32]);
+        let mut btc_feed = feed_with_id(btc, price(60_000));
+        let mut eth_feed = feed_with_id(eth, price(3_000));
+        let feeds = [btc_feed.info(), eth_feed.info()];
+        let clock = clock_at(NOW);
+        assert_eq!(feed_value_at(&feeds, 1, eth, &clock, 10).unwrap(), price(3_000));
+        assert_eq!(feed_value_at(&feeds, 0, eth, &clock, 10).unwrap_err(), Error::from(ErrorCode::UnexpectedFeed));
+        assert_eq!(
+            feed_value_at(&feeds, 2, eth, &clock, 10).unwrap_err(),
+            Error::from(ErrorCode::FeedIndexOutOfRange)
+        );
+    }
+
+    /// A minimal receiver: records each `on_feed_update` addressed to it.
+    struct CallbackRecorder {
+        program: Pubkey,
+        state: Pubkey,
+        received: Vec<(Pubkey, i128, u64)>,
+    }
+
+    impl CallbackRecorder {
+        fn process(&mut self, ix: &anchor_lang::solana_program::instruction::Instruction) {
+            assert_eq!(ix.program_id, self.program);
+            assert_eq!(ix.accounts, vec![AccountMeta::new(self.state, false)]);
+            let (discriminator, mut args) = ix.data.split_at(8);
+            assert_eq!(discriminator, instruction_discriminator("on_feed_update"));
+            let update = FeedCallback::deserialize(&mut args).unwrap();
+            assert!(args.is_empty());
+            self.received.push((update.feed, update.value, update.slot));
+        }
+    }
+
+    #[test]
+    fn receiver_records_one_callback_per_streamed_feed() {
+        let mut recorder = CallbackRecorder {
+            program: Pubkey::new_unique(),
+            state: Pubkey::new_unique(),
+            received: Vec::new(),
+        };
+        let (btc, eth) = (Pubkey::new_unique(), Pubkey::new_unique());
+        for (feed, value) in [(btc, price(60_000)), (eth, price(3_000))] {
+            let update = FeedCallback { feed, value, slot: NOW };
+            recorder.process(&feed_callback_ix(recorder.program, recorder.state, &update).unwrap());
+        }
+        assert_eq!(recorder.received, vec![(btc, price(60_000), NOW), (eth, price(3_000), NOW)]);
+    }
+
+    /// Stands in for an event logger program, keeping what it was sent.
+    struct MockLogger {
+        program: Pubkey,
+        state: Pubkey,
+        logged: Vec<FeedResults>,
+    }
+
+    impl MockLogger {
+        fn process(&mut self, ix: &anchor_lang::solana_program::instruction::Instruction) {
+            assert_eq!(ix.program_id, self.program);
+            assert_eq!(ix.accounts, vec![AccountMeta::new(self.state, false)]);
+            let (discriminator, mut args) = ix.data.split_at(8);
+            assert_eq!(discriminator, instruction_discriminator("log_feed_results"));
+            self.logged.push(FeedResults::deserialize(&mut args).unwrap());
+            assert!(args.is_empty());
+        }
+    }
+
+    #[test]
+    fn mock_logger_receives_the_feed_results() {
+        let mut logger = MockLogger {
+            program: Pubkey::new_unique(),
+            state: Pubkey::new_unique(),
+            logged: Vec::new(),
+        };
+        let (btc, eth) = ([1u8; 32], [2u8; 32]);
+        let mut btc_feed = feed_with_id(btc, price(60_000));
+        let mut eth_feed = feed_with_id(eth, price(3_000));
+        let results = feed_results(&[btc_feed.info(), eth_feed.info()], &clock_at(NOW), 10).unwrap();
+        logger.process(&event_logger_ix(logger.program, logger.state, &results).unwrap());
+
+        assert_eq!(logger.logged.len(), 1);
+        let logged = &logger.logged[0];
+        assert_eq!(logged.slot, NOW);
+        let readings: Vec<([u8; 32], i128)> = logged.readings.iter().map(|r| (r.feed_id, r.value)).collect();
+        assert_eq!(readings, vec![(btc, price(60_000)), (eth, price(3_000))]);
+    }
+
+    #[test]
+    fn fallback_does_not_mask_a_spoofed_primary() {
+        let owner = Pubkey::new_unique();
+        let mut spoofed = TestFeed::new(price(1), NOW);
+        let mut secondary = TestFeed::new(price(101), NOW);
+        let mut spoofed = spoofed.info();
+        spoofed.owner = &owner;
+        let secondary = secondary.info();
+        assert_eq!(
+            value_with_fallback(&spoofed, Some(&secondary), &clock_at(NOW), 10).unwrap_err(),
+            Error::from(ErrorCode::InvalidFeedOwner)
+        );
+    }
+
+    #[test]
+    fn mul_div_ratio_of_large_prices() {
+        // 60000 / 3000 overflows i128 if the numerator is scaled first.
+        assert_eq!(mul_div(price(60_000), SCALE, price(3_000)).unwrap(), price(20));
+        assert_eq!(mul_div(price(3_000), SCALE, price(60_000)).unwrap(), SCALE / 20);
+    }
+
+    #[test]
+    fn mul_div_moneyness_of_high_spot() {
+        // Spot 65000 against a 50000 strike: 1.3 moneyness.
+        assert_eq!(mul_div(price(65_000), SCALE, price(50_000)).unwrap(), 13 * SCALE / 10);
+    }
+
+    #[test]
+    fn mul_div_funding_premium_of_high_priced_perp() {
+        // Mark 60300 over index 60000: a 0.5% premium.
+        let spread = price(60_300) - price(60_000);
+        assert_eq!(mul_div(spread, SCALE, price(60_000)).unwrap(), SCALE / 200);
+    }
+
+    #[test]
+    fn mul_div_signs_truncate_toward_zero() {
+        assert_eq!(mul_div(-7, 1, 2).unwrap(), -3);
+        assert_eq!(mul_div(7, -1, 2).unwrap(), -3);
+        assert_eq!(mul_div(-7, -1, -2).unwrap(), -3);
+        assert_eq!(mul_div(-7, -1, 2).unwrap(), 3);
+    }
+
+    #[test]
+    fn mul_div_rejects_zero_divisor_and_overflow() {
+        assert_eq!(mul_div(1, 1, 0).unwrap_err(), Error::from(ErrorCode::DivideByZero));
+        assert_eq!(mul_div(i128::MAX, 2, 1).unwrap_err(), Error::from(ErrorCode::MathOverflow));
+        assert_eq!(mul_div(i128::MIN, 1, 1).unwrap(), i128::MIN);
+    }
+
+    #[test]
+    fn swap_output_below_slippage_bound_is_rejected() {
+        // 10 tokens at 1.5 each imply 15 out.
+        assert_eq!(check_swap_output(10, 3 * SCALE / 2, 15).unwrap(), 15);
+        assert_eq!(check_swap_output(10, 3 * SCALE / 2, 16).unwrap_err(), Error::from(ErrorCode::OracleGuardrail));
+        // Large amounts at high prices no longer overflow before the division.
+        assert_eq!(check_swap_output(u64::MAX, price(60_000), 0).unwrap(), u64::MAX as i128 * 60_000);
+    }
+
+    #[test]
+    fn normalize_decimals_scales_toward_the_target() {
+        assert_eq!(normalize_decimals(1_500_000, 6, 18).unwrap(), 3 * SCALE / 2);
+        assert_eq!(normalize_decimals(3 * SCALE / 2, 18, 6).unwrap(), 1_500_000);
+        assert_eq!(normalize_decimals(-1_999_999, 6, 0).unwrap(), -1);
+        assert_eq!(normalize_decimals(42, 9, 9).unwrap(), 42);
+        assert_eq!(normalize_decimals(i128::MAX, 0, 1).unwrap_err(), Error::from(ErrorCode::MathOverflow));
+    }
+
+    #[test]
+    fn normalized_average_of_6_and_18_decimal_feeds() {
+        // $2 reported with 6 decimals and $4 reported with 18 decimals, as
+        // returned by feed_value (which adds FEED_DECIMALS): average $3.
+        let usdc_style = (2_000_000 * SCALE, FEED_DECIMALS + 6);
+        let wei_style = (4 * SCALE * SCALE, FEED_DECIMALS + 18);
+        assert_eq!(normalized_average(&[usdc_style, wei_style], FEED_DECIMALS).unwrap(), price(3));
+        // Sub-unit digits of the 6-decimal feed survive the rescaling.
+        let fractional = (1_234_567 * SCALE, FEED_DECIMALS + 6);
+        assert_eq!(normalized_average(&[fractional], FEED_DECIMALS).unwrap(), 1_234_567 * 1_000_000_000_000);
+        assert_eq!(normalized_average(&[], FEED_DECIMALS).unwrap_err(), Error::from(ErrorCode::NoFeedsProvided));
+    }
+
+    #[test]
+    fn load_feed_rejects_accounts_not_owned_by_switchboard() {
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut lamports = 0u64;
+        let mut data = vec![0u8; 8 + std::mem::size_of::<PullFeedAccountData>()];
+        let feed = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert_eq!(load_feed(&feed).err().unwrap(), Error::from(ErrorCode::InvalidFeedOwner));
+    }
+
+    #[test]
+    fn verification_log_wraps_around_keeping_the_newest_records() {
+        let mut log = VerificationLog {
+            entries: [VerificationRecord::default(); VERIFICATION_LOG_LEN],
+            next_index: 0,
+            count: 0,
+            bump: 0,
+        };
+        assert!(log.records().is_empty());
+
+        let total = VERIFICATION_LOG_LEN as u64 + 5;
+        for slot in 0..total {
+            log.push(VerificationRecord::new(slot, Some(slot), true));
+            let expected_len = (slot as usize + 1).min(VERIFICATION_LOG_LEN);
+            assert_eq!(log.records().len(), expected_len);
+        }
+
+        // Oldest first, with the first five records overwritten.
+        let slots: Vec<u64> = log.records().iter().map(|record| record.slot).collect();
+        assert_eq!(slots, (5..total).collect::<Vec<u64>>());
+        assert_eq!(log.next_index, 5);
+        assert_eq!(log.count as usize, VERIFICATION_LOG_LEN);
+    }
+
+    #[test]
+    fn verification_record_keeps_load_failures_apart_from_staleness() {
+        let verified = VerificationRecord::new(1_000, Some(990), true);
+        assert_eq!((verified.staleness, verified.success), (Some(10), true));
+        // A feed that could not be loaded has no staleness, not 1000 slots of it.
+        let failed = VerificationRecord::new(1_000, None, false);
+        assert_eq!((failed.staleness, failed.success), (None, false));
+    }
+
+    /// Sibling hashes for `leaves[index]`, bottom up, matching `merkle_root`.
+    fn merkle_proof(mut level: Vec<[u8; 32]>, mut index: usize) -> Vec<[u8; 32]> {
+        let mut proof = Vec::new();
+        while level.len() > 1 {
+            let sibling = index ^ 1;
+            if sibling < level.len() {
+                proof.push(level[sibling]);
+            }
+            level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => merkle_node(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            index /= 2;
+        }
+        proof
+    }
+
+    fn leaves(count: usize) -> Vec<[u8; 32]> {
+        (0..count).map(|i| feed_leaf(&[i as u8; 32], price(i as i128 + 1), 100 + i as u64)).collect()
+    }
+
+    #[test]
+    fn merkle_proofs_verify_for_every_leaf_and_tree_size() {
+        for count in 1..=MAX_FEEDS {
+            let leaves = leaves(count);
+            let root = merkle_root(leaves.clone());
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = merkle_proof(leaves.clone(), index);
+                assert!(verify_merkle_proof(*leaf, &proof, index, count, root), "{} of {}", index, count);
+                // The same proof does not place the leaf anywhere else.
+                let other = (index + 1) % count;
+                if other != index {
+                    assert!(!verify_merkle_proof(*leaf, &proof, other, count, root));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn merkle_root_does_not_duplicate_odd_nodes() {
+        let abc = leaves(3);
+        let mut abcc = abc.clone();
+        abcc.push(abc[2]);
+        assert_ne!(merkle_root(abc), merkle_root(abcc));
+    }
+
+    #[test]
+    fn merkle_proof_rejects_internal_nodes_and_malformed_proofs() {
+        let leaves = leaves(4);
+        let root = merkle_root(leaves.clone());
+        let left = merkle_node(&leaves[0], &leaves[1]);
+        let right = merkle_node(&leaves[2], &leaves[3]);
+        assert_eq!(merkle_node(&left, &right), root);
+
+        // An internal node presented as a leaf of the 4-leaf tree.
+        assert!(!verify_merkle_proof(left, &[right], 0, 4, root));
+
+        let proof = merkle_proof(leaves.clone(), 0);
+        // Extra trailing siblings, an out-of-range index and an oversized proof all fail.
+        let mut padded = proof.clone();
+        padded.push(right);
+        assert!(!verify_merkle_proof(leaves[0], &padded, 0, 4, root));
+        assert!(!verify_merkle_proof(leaves[0], &proof, 4, 4, root));
+        assert!(!verify_merkle_proof(leaves[0], &[right; MAX_MERKLE_PROOF_LEN + 1], 0, 4, root));
+    }
+
+    #[test]
+    fn check_queue_account_rejects_wrong_owner_and_short_data() {
+        let key = Pubkey::new_unique();
+        let full_len = 8 + std::mem::size_of::<QueueAccountData>();
+
+        let mut lamports = 0u64;
+        let mut data = vec![0u8; full_len];
+        let foreign = Pubkey::new_unique();
+        let queue = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &foreign, false, 0);
+        assert_eq!(check_queue_account(&queue).err().unwrap(), Error::from(ErrorCode::InvalidQueueOwner));
+
+        let mut lamports = 0u64;
+        let mut data = vec![0u8; full_len - 1];
+        let queue = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &ON_DEMAND_MAINNET_PID, false, 0);
+        assert_eq!(check_queue_account(&queue).err().unwrap(), Error::from(ErrorCode::InvalidQueueAccount));
+
+        let mut lamports = 0u64;
+        let mut data = vec![0u8; full_len];
+        let queue = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &ON_DEMAND_DEVNET_PID, false, 0);
+        assert!(check_queue_account(&queue).is_ok());
+    }
+
+    #[test]
+    fn load_feed_rejects_non_feed_accounts_without_panicking() {
+        let key = Pubkey::new_unique();
+        let full_len = 8 + std::mem::size_of::<PullFeedAccountData>();
+        // Empty, truncated, and full-length with a wrong discriminator.
+        for len in [0, 8, full_len - 1, full_len] {
+            let mut lamports = 0u64;
+            let mut data = vec![0u8; len];
+            let feed = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &ON_DEMAND_MAINNET_PID, false, 0);
+            assert_eq!(
+                load_feed(&feed).err().unwrap(),
+                Error::from(ErrorCode::InvalidFeedAccount),
+                "{} bytes",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn to_scaled_and_from_scaled_round_trip() {
+        assert_eq!(to_scaled(1.5, 18), 1_500_000_000_000_000_000);
+        assert_eq!(to_scaled(-1.5, 6), -1_500_000);
+        assert_eq!(to_scaled(60_000.0, 18), price(60_000));
+        // Half rounds away from zero.
+        assert_eq!(to_scaled(2.5, 0), 3);
+        assert_eq!(to_scaled(-2.5, 0), -3);
+
+        for value in [0.0, 1.0, -2.25, 0.000_001, 3_000.123_456, 60_000.987_654_321] {
+            for decimals in [6, 8, 18] {
+                let round_trip = from_scaled(to_scaled(value, decimals), decimals);
+                assert!(
+                    (round_trip - value).abs() <= value.abs() * 1e-12 + 10f64.powi(-(decimals as i32)),
+                    "{} at {} decimals came back as {}",
+                    value,
+                    decimals,
+                    round_trip
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn to_scaled_saturates_outside_the_i128_range() {
+        assert_eq!(to_scaled(1e30, 18), i128::MAX);
+        assert_eq!(to_scaled(-1e30, 18), i128::MIN);
+        assert_eq!(to_scaled(f64::NAN, 18), 0);
+        // The largest representable value still converts back to a finite float.
+        let largest = from_scaled(i128::MAX, 18);
+        assert!(largest.is_finite() && (largest - 1.701_411_834_604_692_3e20).abs() < 1e6);
+    }
+
+    fn empty_twap() -> Twap {
+        Twap {
+            feed: Pubkey::new_unique(),
+            cumulative_price: 0,
+            total_slots: 0,
+            last_price: 0,
+            last_update_slot: 0,
+            last_result_slot: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn twap_weights_prices_by_slots_current() {
+        let mut twap = empty_twap();
+        twap.record(price(100), 9, 10).unwrap();
+        assert_eq!(twap.average().err().unwrap(), Error::from(ErrorCode::TwapNotReady));
+
+        // 100 for 10 slots, then 200 for 30 slots: (1000 + 6000) / 40 = 175.
+        twap.record(price(200), 19, 20).unwrap();
+        twap.record(price(50), 49, 50).unwrap();
+        assert_eq!(twap.total_slots, 40);
+        assert_eq!(twap.average().unwrap(), price(175));
+        // The newest price only counts once time has passed.
+        assert_eq!(twap.last_price, price(50));
+    }
+
+    #[test]
+    fn twap_rejects_repeated_or_older_results() {
+        let mut twap = empty_twap();
+        twap.record(price(100), 9, 10).unwrap();
+        for result_slot in [9, 5] {
+            assert_eq!(
+                twap.record(price(300), result_slot, 20).err().unwrap(),
+                Error::from(ErrorCode::DuplicateResult)
+            );
+        }
+        // A rejected update leaves the accumulator untouched.
+        assert_eq!((twap.total_slots, twap.last_price, twap.last_update_slot), (0, price(100), 10));
+    }
+
+    #[test]
+    fn ema_moves_alpha_of_the_way_to_each_price() {
+        let mut ema = Ema { feed: Pubkey::new_unique(), value: 0, alpha_bps: 2_500, last_result_slot: 0, bump: 0 };
+        ema.record(price(100), 10).unwrap();
+        assert_eq!(ema.value, price(100));
+        // 100 + 25% of (200 - 100) = 125, then 125 + 25% of (25 - 125) = 100.
+        ema.record(price(200), 11).unwrap();
+        assert_eq!(ema.value, price(125));
+        ema.record(price(25), 12).unwrap();
+        assert_eq!(ema.value, price(100));
+
+        let mut full = Ema { feed: Pubkey::new_unique(), value: 0, alpha_bps: 10_000, last_result_slot: 0, bump: 0 };
+        full.record(price(100), 1).unwrap();
+        full.record(price(-40), 2).unwrap();
+        assert_eq!(full.value, price(-40));
+    }
+
+    #[test]
+    fn ema_rejects_repeated_or_older_results() {
+        let mut ema = Ema { feed: Pubkey::new_unique(), value: 0, alpha_bps: 5_000, last_result_slot: 0, bump: 0 };
+        ema.record(price(100), 10).unwrap();
+        for result_slot in [10, 3] {
+            assert_eq!(
+                ema.record(price(500), result_slot).err().unwrap(),
+                Error::from(ErrorCode::DuplicateResult)
+            );
+        }
+        assert_eq!((ema.value, ema.last_result_slot), (price(100), 10));
+    }
+
+    #[test]
+    fn require_agreement_accepts_exactly_the_tolerance() {
+        let disagree = || Error::from(ErrorCode::SourcesDisagree);
+        let a = price(100);
+        let half_percent = SCALE / 2;
+        // 50 bps of 100 is 0.5 either side, inclusive.
+        assert!(require_agreement(a, a + half_percent, 50).is_ok());
+        assert!(require_agreement(a, a - half_percent, 50).is_ok());
+        assert_eq!(require_agreement(a, a + half_percent + 1, 50).err().unwrap(), disagree());
+        assert_eq!(require_agreement(a, a - half_percent - 1, 50).err().unwrap(), disagree());
+        // Zero tolerance only accepts equal values.
+        assert!(require_agreement(a, a, 0).is_ok());
+        assert_eq!(require_agreement(a, a + 1, 0).err().unwrap(), disagree());
+        // Tolerance is relative to `a`, including for negative values and zero.
+        assert!(require_agreement(-a, -a - half_percent, 50).is_ok());
+        assert_eq!(require_agreement(-a, -a - half_percent - 1, 50).err().unwrap(), disagree());
+        assert!(require_agreement(0, 0, 50).is_ok());
+        assert_eq!(require_agreement(0, 1, 10_000).err().unwrap(), disagree());
+    }
+
+    #[test]
+    fn require_agreement_reports_overflow_instead_of_panicking() {
+        let overflow = Error::from(ErrorCode::MathOverflow);
+        assert_eq!(require_agreement(i128::MAX, -1, 50).err().unwrap(), overflow);
+        assert_eq!(require_agreement(i128::MAX / 2, -(i128::MAX / 2), 50).err().unwrap(), overflow);
+    }
 }
@@ -0,0 +1,382 @@
+use anchor_lang::prelude::*;
+use std::cell::Ref;
+use anchor_spl::token::{Mint, TokenAccount};
+use switchboard_on_demand::on_demand::accounts::pull_feed::PullFeedAccountData;
+use switchboard_on_demand::program_id::{ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
+
+declare_id!("2BRHTZ7aMggsk4xM7n5uR1nwM2gguvVUR3H3zJSgboKS");
+
+// Upper bound on the number of feeds a single instruction will process.
+pub const MAX_FEEDS: usize = 16;
+
+/// Parse a pull feed account, rejecting accounts not owned by Switchboard
+/// and accounts too short to hold one instead of letting the SDK panic on
+/// truncated data.
+pub fn load_feed<'a>(feed: &'a AccountInfo) -> Result<Ref<'a, PullFeedAccountData>> {
+    check_feed_owner(feed)?;
+    let feed_account = feed.data.borrow();
+    let min_len = 8 + std::mem::size_of::<PullFeedAccountData>();
+    if feed_account.len() < min_len {
+        msg!("Feed account holds {} bytes, expected at least {}", feed_account.len(), min_len);
+        return Err(ErrorCode::InvalidFeedAccount.into());
+    }
+    PullFeedAccountData::parse(feed_account).map_err(|e| {
+        msg!("Parse Error: {:?}", e);
+        ErrorCode::InvalidFeedAccount.into()
+    })
+}
+
+/// Parse a pull feed and return its median value as an 18-decimal fixed point
+/// integer, rejecting results older than `max_stale_slots`.
+pub fn feed_value(feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+    let feed = load_feed(feed)?;
+    // A result newer than the clock usually means a local test validator was
+    // never advanced; report it separately from ordinary staleness.
+    if feed.result.slot > clock.slot {
+        msg!(
+            "Feed result slot {} is ahead of the current slot {}; is the test clock behind?",
+            feed.result.slot,
+            clock.slot
+        );
+        return Err(ErrorCode::FeedFromFutureSlot.into());
+    }
+    let value = feed
+        .get_value(clock, max_stale_slots, 1, false)
+        .map_err(|e| {
+            msg!("Get Value Error: {:?}", e);
+            ErrorCode::StaleFeed
+        })?;
+    Ok(value.mantissa())
+}
+
+/// Reject accounts that are not owned by the Switchboard On-Demand program.
+pub fn check_feed_owner(feed: &AccountInfo) -> Result<()> {
+    if feed.owner != &ON_DEMAND_MAINNET_PID && feed.owner != &ON_DEMAND_DEVNET_PID {
+        msg!("Feed {} is owned by {}", feed.key(), feed.owner);
+        return Err(ErrorCode::InvalidFeedOwner.into());
+    }
+    Ok(())
+}
+
+/// Parse a pull feed and return the hash of its job definitions.
+pub fn feed_hash(feed: &AccountInfo) -> Result<[u8; 32]> {
+    let feed = load_feed(feed)?;
+    Ok(feed.feed_hash)
+}
+
+// 10^18, the fixed point scale of feed values.
+pub const SCALE: i128 = 1_000_000_000_000_000_000;
+
+/// `a * b / d` for unsigned values, computed with a 256-bit intermediate so
+/// the product cannot overflow. Returns None if `d` is zero or the quotient
+/// does not fit in a u128.
+fn mul_div_u128(a: u128, b: u128, d: u128) -> Option<u128> {
+    if d == 0 {
+        return None;
+    }
+    // 128 x 128 -> 256 bit product from 64-bit limbs.
+    const MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_hi = a_hi * b_hi;
+    let cross = (lo_lo >> 64) + (hi_lo & MASK) + (lo_hi & MASK);
+    let lo = (cross << 64) | (lo_lo & MASK);
+    let hi = hi_hi + (hi_lo >> 64) + (lo_hi >> 64) + (cross >> 64);
+    if hi >= d {
+        return None;
+    }
+    // Shift-subtract long division of hi:lo by d; the remainder stays below d.
+    let mut remainder = hi;
+    let mut quotient = 0u128;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> bit) & 1);
+        if carry == 1 || remainder >= d {
+            remainder = remainder.wrapping_sub(d);
+            quotient |= 1 << bit;
+        }
+    }
+    Some(quotient)
+}
+
+/// `a * b / d`, truncated toward zero like `/`, without overflowing on the
+/// intermediate product. Use it to rescale 18-decimal values, e.g.
+/// `mul_div(numerator, SCALE, denominator)` for a fixed point ratio.
+pub fn mul_div(a: i128, b: i128, d: i128) -> Result<i128> {
+    require!(d != 0, ErrorCode::DivideByZero);
+    let negative = (a < 0) ^ (b < 0) ^ (d < 0);
+    let magnitude = mul_div_u128(a.unsigned_abs(), b.unsigned_abs(), d.unsigned_abs())
+        .ok_or(ErrorCode::MathOverflow)?;
+    if negative {
+        0i128.checked_sub_unsigned(magnitude).ok_or(ErrorCode::MathOverflow.into())
+    } else {
+        i128::try_from(magnitude).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+}
+
+#[program]
+pub mod sb_on_demand_vault_nav {
+    use super::*;
+
+    pub fn init_nav_config(ctx: Context<InitNavConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.nav_config;
+        config.authority = ctx.accounts.authority.key();
+        config.assets = Vec::new();
+        config.bump = ctx.bumps.nav_config;
+        Ok(())
+    }
+
+    // Map a token mint to the feed that prices it, replacing any existing entry.
+    pub fn set_nav_asset(ctx: Context<SetNavAsset>, feed_id: [u8; 32]) -> Result<()> {
+        let mint = &ctx.accounts.mint;
+        let config = &mut ctx.accounts.nav_config;
+        let asset = NavAsset { mint: mint.key(), decimals: mint.decimals, feed_id };
+        match config.assets.iter_mut().find(|asset| asset.mint == mint.key()) {
+            Some(existing) => *existing = asset,
+            None => {
+                require!(config.assets.len() < MAX_FEEDS, ErrorCode::TooManyFeeds);
+                config.assets.push(asset);
+            }
+        }
+        Ok(())
+    }
+
+    // Net asset value of the vault: the sum of balance * price over its token
+    // accounts. The remaining accounts hold `token_account_count` token
+    // accounts followed by the feeds pricing them, in any order.
+    pub fn compute_nav<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ComputeNav<'info>>,
+        token_account_count: u8,
+        max_stale_slots: u64,
+    ) -> Result<i128> {
+        let count = token_account_count as usize;
+        require!(count <= ctx.remaining_accounts.len(), ErrorCode::MissingTokenAccounts);
+        let (token_accounts, feeds) = ctx.remaining_accounts.split_at(count);
+        let config = &ctx.accounts.nav_config;
+        let mut holdings = Vec::with_capacity(count);
+        for (i, info) in token_accounts.iter().enumerate() {
+            if token_accounts[..i].iter().any(|other| other.key == info.key) {
+                msg!("Token account {} supplied more than once", info.key());
+                return Err(ErrorCode::DuplicateTokenAccount.into());
+            }
+            let token_account = Account::<TokenAccount>::try_from(info)?;
+            require_keys_eq!(token_account.owner, config.authority, ErrorCode::UnexpectedTokenAccount);
+            holdings.push((token_account.mint, token_account.amount));
+        }
+        let nav = config.nav(&holdings, feeds, &Clock::get()?, max_stale_slots)?;
+        msg!("NAV: {}", nav);
+        Ok(nav)
+    }
+}
+
+// === Accounts ===
+#[account]
+pub struct NavConfig {
+    pub authority: Pubkey,     // Owner of the vault's token accounts
+    pub assets: Vec<NavAsset>, // Mint to feed map
+    pub bump: u8,
+}
+
+impl NavConfig {
+    // Sum of amount * price over `holdings` of (mint, native amount), each
+    // priced by the feed in `feeds` that its mint is mapped to.
+    pub fn nav(
+        &self,
+        holdings: &[(Pubkey, u64)],
+        feeds: &[AccountInfo],
+        clock: &Clock,
+        max_stale_slots: u64,
+    ) -> Result<i128> {
+        require!(feeds.len() <= MAX_FEEDS, ErrorCode::TooManyFeeds);
+        let mut available: Vec<([u8; 32], &AccountInfo)> = Vec::with_capacity(feeds.len());
+        for feed in feeds.iter() {
+            available.push((feed_hash(feed)?, feed));
+        }
+
+        let mut nav: i128 = 0;
+        for (mint, amount) in holdings.iter() {
+            let asset = self.assets.iter().find(|asset| asset.mint == *mint).ok_or(ErrorCode::MissingFeed)?;
+            let (_, feed) = available
+                .iter()
+                .find(|(hash, _)| *hash == asset.feed_id)
+                .ok_or(ErrorCode::MissingFeed)?;
+            let price = feed_value(feed, clock, max_stale_slots)?;
+            let unit = 10i128.checked_pow(asset.decimals as u32).ok_or(ErrorCode::MathOverflow)?;
+            let value = mul_div(*amount as i128, price, unit)?;
+            msg!("{}: {} @ {} = {}", mint, amount, price, value);
+            nav = nav.checked_add(value).ok_or(ErrorCode::MathOverflow)?;
+        }
+        Ok(nav)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct NavAsset {
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub feed_id: [u8; 32],
+}
+
+// === Instructions ===
+#[derive(Accounts)]
+pub struct InitNavConfig<'info> {
+    #[account(init,
+        payer = authority,
+        seeds = [b"navConfig".as_ref(), authority.key().as_ref()],
+        space = 8 + 32 + 4 + (32 + 1 + 32) * MAX_FEEDS + 1,
+        bump)]
+    pub nav_config: Account<'info, NavConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetNavAsset<'info> {
+    #[account(mut,
+        seeds = [b"navConfig".as_ref(), authority.key().as_ref()],
+        bump = nav_config.bump,
+        has_one = authority)]
+    pub nav_config: Account<'info, NavConfig>,
+    pub mint: Account<'info, Mint>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ComputeNav<'info> {
+    #[account(seeds = [b"navConfig".as_ref(), nav_config.authority.as_ref()], bump = nav_config.bump)]
+    pub nav_config: Account<'info, NavConfig>,
+}
+
+// === Errors ===
+#[error_code]
+pub enum ErrorCode {
+    #[msg("The account is not a valid Switchboard pull feed.")]
+    InvalidFeedAccount,
+    #[msg("The feed value is stale.")]
+    StaleFeed,
+    #[msg("Too many feeds requested.")]
+    TooManyFeeds,
+    #[msg("The feed account is not owned by the Switchboard program.")]
+    InvalidFeedOwner,
+    #[msg("Arithmetic overflow.")]
+    MathOverflow,
+    #[msg("The feed result is from a slot ahead of the current clock.")]
+    FeedFromFutureSlot,
+    #[msg("Division by zero.")]
+    DivideByZero,
+    #[msg("A referenced feed was not supplied.")]
+    MissingFeed,
+    #[msg("The token account is not owned by the vault authority.")]
+    UnexpectedTokenAccount,
+    #[msg("Fewer remaining accounts were supplied than token_account_count.")]
+    MissingTokenAccounts,
+    #[msg("A token account was supplied more than once.")]
+    DuplicateTokenAccount,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(whole: i128) -> i128 {
+        whole * SCALE
+    }
+
+    // Slot of the test clock; fixture results are produced a few slots before it.
+    const NOW: u64 = 1_000;
+
+    fn clock_at(slot: u64) -> Clock {
+        Clock { slot, ..Clock::default() }
+    }
+
+    /// Backing storage for a Switchboard pull feed account whose only oracle
+    /// reported `value` at `slot`, owned by the on-demand program.
+    struct TestFeed {
+        key: Pubkey,
+        lamports: u64,
+        buf: Vec<u8>,
+        start: usize,
+    }
+
+    impl TestFeed {
+        /// Fixture for `value` at `slot`, letting `edit` adjust the account
+        /// before it is written.
+        fn with(value: i128, slot: u64, edit: impl FnOnce(&mut PullFeedAccountData)) -> Self {
+            // SAFETY: the account is a zero-copy plain-old-data struct, for
+            // which all zero bytes are a valid value.
+            let mut feed: PullFeedAccountData = unsafe { std::mem::zeroed() };
+            feed.result.value = value;
+            feed.result.slot = slot;
+            feed.submissions[0].oracle = Pubkey::new_unique();
+            feed.submissions[0].slot = slot;
+            feed.submissions[0].value = value;
+            edit(&mut feed);
+            // SAFETY: as above, the struct has no padding-sensitive invariants.
+            let bytes = unsafe {
+                std::slice::from_raw_parts(
+                    &feed as *const PullFeedAccountData as *const u8,
+                    std::mem::size_of::<PullFeedAccountData>(),
+                )
+            };
+            // The SDK casts the bytes after the discriminator in place, so they
+            // must be 16-byte aligned for the i128 fields.
+            let mut buf = vec![0u8; 8 + bytes.len() + 16];
+            let start = (16 - (buf.as_ptr() as usize + 8) % 16) % 16;
+            let discriminator = anchor_lang::solana_program::hash::hash(b"account:PullFeedAccountData");
+            buf[start..start + 8].copy_from_slice(&discriminator.to_bytes()[..8]);
+            buf[start + 8..start + 8 + bytes.len()].copy_from_slice(bytes);
+            Self { key: Pubkey::new_unique(), lamports: 1, buf, start }
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            let len = 8 + std::mem::size_of::<PullFeedAccountData>();
+            let data = &mut self.buf[self.start..self.start + len];
+            AccountInfo::new(&self.key, false, false, &mut self.lamports, data, &ON_DEMAND_MAINNET_PID, false, 0)
+        }
+    }
+
+    /// A fresh feed whose job definitions hash to `feed_id`.
+    fn feed_with_id(feed_id: [u8; 32], value: i128) -> TestFeed {
+        TestFeed::with(value, NOW, |feed| feed.feed_hash = feed_id)
+    }
+
+    #[test]
+    fn nav_of_a_two_asset_vault() {
+        let (sol_id, btc_id) = ([1u8; 32], [2u8; 32]);
+        let (sol, btc, unmapped) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let config = NavConfig {
+            authority: Pubkey::new_unique(),
+            assets: vec![
+                NavAsset { mint: sol, decimals: 9, feed_id: sol_id },
+                NavAsset { mint: btc, decimals: 8, feed_id: btc_id },
+            ],
+            bump: 255,
+        };
+        let mut sol_feed = feed_with_id(sol_id, price(150));
+        let mut btc_feed = feed_with_id(btc_id, price(60_000));
+        let feeds = [btc_feed.info(), sol_feed.info()];
+        let clock = clock_at(NOW);
+        // 20 SOL and 0.5 BTC: 3000 + 30000.
+        let holdings = [(sol, 20 * 10u64.pow(9)), (btc, 5 * 10u64.pow(7))];
+        assert_eq!(config.nav(&holdings, &feeds, &clock, 10).unwrap(), price(33_000));
+
+        // A mint without a mapped feed, or a mapped feed that was not passed.
+        assert_eq!(
+            config.nav(&[(unmapped, 1)], &feeds, &clock, 10).unwrap_err(),
+            Error::from(ErrorCode::MissingFeed)
+        );
+        assert_eq!(
+            config.nav(&holdings, &feeds[..1], &clock, 10).unwrap_err(),
+            Error::from(ErrorCode::MissingFeed)
+        );
+        let mut stale_btc = TestFeed::with(price(60_000), NOW - 30, |feed| feed.feed_hash = btc_id);
+        assert_eq!(
+            config.nav(&holdings, &[stale_btc.info(), feeds[1].clone()], &clock, 10).unwrap_err(),
+            Error::from(ErrorCode::StaleFeed)
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use anchor_lang::prelude::*;
+use std::cell::Ref;
+use anchor_spl::token::{self, Mint, Token, TokenAccount};
+use switchboard_on_demand::on_demand::accounts::pull_feed::PullFeedAccountData;
+use switchboard_on_demand::program_id::{ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
+
+declare_id!("FuuHJ5YojM6ogSeZD8aBmESPEwn24wCbnGmkKLBRPouc");
+
+/// Parse a pull feed account, rejecting accounts not owned by Switchboard
+/// and accounts too short to hold one instead of letting the SDK panic on
+/// truncated data.
+pub fn load_feed<'a>(feed: &'a AccountInfo) -> Result<Ref<'a, PullFeedAccountData>> {
+    check_feed_owner(feed)?;
+    let feed_account = feed.data.borrow();
+    let min_len = 8 + std::mem::size_of::<PullFeedAccountData>();
+    if feed_account.len() < min_len {
+        msg!("Feed account holds {} bytes, expected at least {}", feed_account.len(), min_len);
+        return Err(ErrorCode::InvalidFeedAccount.into());
+    }
+    PullFeedAccountData::parse(feed_account).map_err(|e| {
+        msg!("Parse Error: {:?}", e);
+        ErrorCode::InvalidFeedAccount.into()
+    })
+}
+
+/// Parse a pull feed and return its median value as an 18-decimal fixed point
+/// integer, rejecting results older than `max_stale_slots`.
+pub fn feed_value(feed: &AccountInfo, clock: &Clock, max_stale_slots: u64) -> Result<i128> {
+    let feed = load_feed(feed)?;
+    // A result newer than the clock usually means a local test validator was
+    // never advanced; report it separately from ordinary staleness.
+    if feed.result.slot > clock.slot {
+        msg!(
+            "Feed result slot {} is ahead of the current slot {}; is the test clock behind?",
+            feed.result.slot,
+            clock.slot
+        );
+        return Err(ErrorCode::FeedFromFutureSlot.into());
+    }
+    let value = feed
+        .get_value(clock, max_stale_slots, 1, false)
+        .map_err(|e| {
+            msg!("Get Value Error: {:?}", e);
+            ErrorCode::StaleFeed
+        })?;
+    Ok(value.mantissa())
+}
+
+/// Reject accounts that are not owned by the Switchboard On-Demand program.
+pub fn check_feed_owner(feed: &AccountInfo) -> Result<()> {
+    if feed.owner != &ON_DEMAND_MAINNET_PID && feed.owner != &ON_DEMAND_DEVNET_PID {
+        msg!("Feed {} is owned by {}", feed.key(), feed.owner);
+        return Err(ErrorCode::InvalidFeedOwner.into());
+    }
+    Ok(())
+}
+
+// 10^18, the fixed point scale of feed values.
+pub const SCALE: i128 = 1_000_000_000_000_000_000;
+
+/// `a * b / d` for unsigned values, computed with a 256-bit intermediate so
+/// the product cannot overflow. Returns None if `d` is zero or the quotient
+/// does not fit in a u128.
+fn mul_div_u128(a: u128, b: u128, d: u128) -> Option<u128> {
+    if d == 0 {
+        return None;
+    }
+    // 128 x 128 -> 256 bit product from 64-bit limbs.
+    const MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_hi = a_hi * b_hi;
+    let cross = (lo_lo >> 64) + (hi_lo & MASK) + (lo_hi & MASK);
+    let lo = (cross << 64) | (lo_lo & MASK);
+    let hi = hi_hi + (hi_lo >> 64) + (lo_hi >> 64) + (cross >> 64);
+    if hi >= d {
+        return None;
+    }
+    // Shift-subtract long division of hi:lo by d; the remainder stays below d.
+    let mut remainder = hi;
+    let mut quotient = 0u128;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> bit) & 1);
+        if carry == 1 || remainder >= d {
+            remainder = remainder.wrapping_sub(d);
+            quotient |= 1 << bit;
+        }
+    }
+    Some(quotient)
+}
+
+/// `a * b / d`, truncated toward zero like `/`, without overflowing on the
+/// intermediate product. Use it to rescale 18-decimal values, e.g.
+/// `mul_div(numerator, SCALE, denominator)` for a fixed point ratio.
+pub fn mul_div(a: i128, b: i128, d: i128) -> Result<i128> {
+    require!(d != 0, ErrorCode::DivideByZero);
+    let negative = (a < 0) ^ (b < 0) ^ (d < 0);
+    let magnitude = mul_div_u128(a.unsigned_abs(), b.unsigned_abs(), d.unsigned_abs())
+        .ok_or(ErrorCode::MathOverflow)?;
+    if negative {
+        0i128.checked_sub_unsigned(magnitude).ok_or(ErrorCode::MathOverflow.into())
+    } else {
+        i128::try_from(magnitude).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+}
+
+/// 18-decimal value of `amount` base units of a token with `decimals`
+/// decimals, at `price` per whole token.
+pub fn token_value(amount: u64, price: i128, decimals: u8) -> Result<i128> {
+    let token_scale = 10i128.checked_pow(decimals as u32).ok_or(ErrorCode::MathOverflow)?;
+    mul_div(amount as i128, price, token_scale)
+}
+
+#[program]
+pub mod sb_on_demand_vault {
+    use super::*;
+
+    // Create a vault holding deposits of `mint`, valued using `feed` (USD per
+    // whole token). Vaults are keyed by their authority as well as the mint, so
+    // nobody can squat the vault of a mint with a feed of their choosing.
+    pub fn init_vault(ctx: Context<InitVault>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.authority = ctx.accounts.authority.key();
+        vault.mint = ctx.accounts.mint.key();
+        vault.feed = ctx.accounts.feed.key();
+        vault.vault_tokens = ctx.accounts.vault_tokens.key();
+        vault.bump = ctx.bumps.vault;
+        Ok(())
+    }
+
+    pub fn init_position(ctx: Context<InitPosition>) -> Result<()> {
+        let position = &mut ctx.accounts.position;
+        position.vault = ctx.accounts.vault.key();
+        position.owner = ctx.accounts.owner.key();
+        position.bump = ctx.bumps.position;
+        Ok(())
+    }
+
+    // Deposit tokens into the vault, snapshotting their USD value at the
+    // current feed price. Deposits are rejected if the price is stale.
+    pub fn deposit(ctx: Context<Deposit>, amount: u64, max_stale_slots: u64) -> Result<()> {
+        let clock = Clock::get()?;
+        let price = feed_value(&ctx.accounts.feed, &clock, max_stale_slots)?;
+        let usd_value = token_value(amount, price, ctx.accounts.mint.decimals)?;
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.owner_tokens.to_account_info(),
+                    to: ctx.accounts.vault_tokens.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let position = &mut ctx.accounts.position;
+        position.amount = position.amount.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        position.usd_value = position.usd_value.checked_add(usd_value).ok_or(ErrorCode::MathOverflow)?;
+        position.last_deposit_price = price;
+        position.last_deposit_slot = clock.slot;
+        msg!("Deposited {} at price {} (usd value {})", amount, price, usd_value);
+        Ok(())
+    }
+}
+
+// === Accounts ===
+#[account]
+pub struct Vault {
+    pub authority: Pubkey, // Created the vault and chose its feed
+    pub mint: Pubkey,
+    pub feed: Pubkey, // USD price of one whole token of `mint`
+    pub vault_tokens: Pubkey,
+    pub bump: u8,
+}
+
+#[account]
+pub struct Position {
+    pub vault: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub usd_value: i128, // 18-decimal USD value snapshotted at deposit time
+    pub last_deposit_price: i128,
+    pub last_deposit_slot: u64,
+    pub bump: u8,
+}
+
+// === Instructions ===
+#[derive(Accounts)]
+pub struct InitVault<'info> {
+    #[account(init,
+        payer = authority,
+        seeds = [b"vault".as_ref(), mint.key().as_ref(), authority.key().as_ref()],
+        space = 8 + 32 + 32 + 32 + 32 + 1,
+        bump)]
+    pub vault: Account<'info, Vault>,
+    #[account(init,
+        payer = authority,
+        seeds = [b"vaultTokens".as_ref(), vault.key().as_ref()],
+        token::mint = mint,
+        token::authority = vault,
+        bump)]
+    pub vault_tokens: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitPosition<'info> {
+    #[account(init,
+        payer = owner,
+        seeds = [b"position".as_ref(), vault.key().as_ref(), owner.key().as_ref()],
+        space = 8 + 32 + 32 + 8 + 16 + 16 + 8 + 1,
+        bump)]
+    pub position: Account<'info, Position>,
+    pub vault: Account<'info, Vault>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(mut,
+        seeds = [b"position".as_ref(), vault.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump)]
+    pub position: Account<'info, Position>,
+    #[account(has_one = mint, has_one = feed, has_one = vault_tokens)]
+    pub vault: Account<'info, Vault>,
+    #[account(mut)]
+    pub vault_tokens: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = owner)]
+    pub owner_tokens: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    /// CHECK: via switchboard sdk
+    pub feed: AccountInfo<'info>,
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+// === Errors ===
+#[error_code]
+pub enum ErrorCode {
+    #[msg("The account is not a valid Switchboard pull feed.")]
+    InvalidFeedAccount,
+    #[msg("The feed value is stale.")]
+    StaleFeed,
+    #[msg("The feed account is not owned by the Switchboard program.")]
+    InvalidFeedOwner,
+    #[msg("Arithmetic overflow.")]
+    MathOverflow,
+    #[msg("The feed result is from a slot ahead of the current clock.")]
+    FeedFromFutureSlot,
+    #[msg("Division by zero.")]
+    DivideByZero,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(whole: i128) -> i128 {
+        whole * SCALE
+    }
+
+    #[test]
+    fn token_value_of_deposit_at_known_price() {
+        // 1.5 tokens with 6 decimals at $2000 are worth $3000.
+        assert_eq!(token_value(1_500_000, price(2_000), 6).unwrap(), price(3_000));
+        // amount * price alone would overflow i128 here.
+        assert_eq!(token_value(u64::MAX, price(100), 9).unwrap(), u64::MAX as i128 * (price(100) / 1_000_000_000));
+        assert_eq!(token_value(1, price(1), 40).unwrap_err(), Error::from(ErrorCode::MathOverflow));
+    }
+
+    #[test]
+    fn vaults_are_keyed_by_authority() {
+        let mint = Pubkey::new_unique();
+        let vault = |authority: &Pubkey| {
+            Pubkey::find_program_address(&[b"vault", mint.as_ref(), authority.as_ref()], &crate::ID).0
+        };
+        assert_ne!(vault(&Pubkey::new_unique()), vault(&Pubkey::new_unique()));
+    }
+}
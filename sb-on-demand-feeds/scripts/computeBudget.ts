@@ -0,0 +1,38 @@
+import * as sb from "@switchboard-xyz/on-demand";
+import yargs from "yargs";
+import { meteredReadTx, myAnchorProgram } from "./utils";
+import { PublicKey } from "@solana/web3.js";
+
+const argv = yargs(process.argv).options({
+  feeds: { type: "array", string: true, required: true },
+  units: { type: "number", default: 1_400_000 },
+  maxStaleSlots: { type: "number", default: 100 },
+}).argv;
+
+(async function main() {
+  const { keypair, connection, program } = await sb.AnchorUtils.loadEnv();
+  const feeds = argv.feeds.map((feed) => new PublicKey(feed));
+  const commitment = "processed";
+  const demoPath = "target/deploy/sb_on_demand_solana-keypair.json";
+  const demo = await myAnchorProgram(program.provider, demoPath).catch((e) => {
+    throw new Error("Failed to load demo program. Was it deployed?");
+  });
+
+  // Request the compute limit explicitly instead of relying on asV0Tx's
+  // simulation-based estimate, so the budget needed per feed is visible.
+  const tx = await meteredReadTx(
+    program,
+    demo,
+    keypair,
+    feeds,
+    argv.maxStaleSlots,
+    argv.units
+  );
+
+  const sim = await connection.simulateTransaction(tx, { commitment });
+  console.log(sim.value.logs.filter((log) => log.includes("consumption")));
+  console.log(`Units consumed: ${sim.value.unitsConsumed} of ${argv.units}`);
+  if (sim.value.err) throw new Error(`Simulation failed: ${sim.value.err}`);
+  const sig = await connection.sendTransaction(tx);
+  console.log(`Transaction sent: ${sig}`);
+})();
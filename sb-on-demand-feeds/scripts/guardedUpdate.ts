@@ -0,0 +1,57 @@
+import * as sb from "@switchboard-xyz/on-demand";
+import yargs from "yargs";
+import Big from "big.js";
+import { myAnchorProgram } from "./utils";
+import { PublicKey } from "@solana/web3.js";
+
+const argv = yargs(process.argv).options({
+  feed: { type: "string", required: true },
+  min: { type: "string", required: true, describe: "Lowest acceptable value" },
+  max: { type: "string", required: true, describe: "Highest acceptable value" },
+}).argv;
+
+(async function main() {
+  const { keypair, connection, program } = await sb.AnchorUtils.loadEnv();
+  const feed = new PublicKey(argv.feed);
+  const feedAccount = new sb.PullFeed(program, feed);
+  const [min, max] = [new Big(argv.min), new Big(argv.max)];
+  const demoPath = "target/deploy/sb_on_demand_solana-keypair.json";
+  const demo = await myAnchorProgram(program.provider, demoPath).catch((e) => {
+    throw new Error("Failed to load demo program. Was it deployed?");
+  });
+
+  // Fetch the oracle responses first and check them before spending a
+  // transaction on an update we would not want to rely on.
+  const [pullIx, responses, success] = await feedAccount.fetchUpdateIx({
+    numSignatures: 3,
+  });
+  if (!success) throw new Error(`Errors: ${responses.map((x) => x.error)}`);
+
+  const outOfBand = responses.filter(
+    (x) => x.value === null || x.value.lt(min) || x.value.gt(max)
+  );
+  if (outOfBand.length > 0) {
+    console.warn(
+      `Aborting: ${outOfBand.length} response(s) outside [${min}, ${max}]: ` +
+        outOfBand.map((x) => x.value?.toString() ?? x.error).join(", ")
+    );
+    return;
+  }
+  console.log(
+    `All responses within [${min}, ${max}]: ` +
+      responses.map((x) => x.value.toString()).join(", ")
+  );
+
+  const myIx = await demo.methods.test().accounts({ feed }).instruction();
+  const lutOwners = [...responses.map((x) => x.oracle), feedAccount];
+  const tx = await sb.asV0Tx({
+    connection,
+    ixs: [pullIx, myIx],
+    signers: [keypair],
+    computeUnitPrice: 200_000,
+    computeUnitLimitMultiple: 1.3,
+    lookupTables: await sb.loadLookupTables(lutOwners),
+  });
+  const sig = await connection.sendTransaction(tx);
+  console.log(`Transaction sent: ${sig}`);
+})();
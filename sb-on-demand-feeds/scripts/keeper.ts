@@ -0,0 +1,76 @@
+import * as anchor from "@coral-xyz/anchor";
+import * as sb from "@switchboard-xyz/on-demand";
+import {
+  AddressLookupTableAccount,
+  Connection,
+  Keypair,
+  PublicKey,
+  TransactionInstruction,
+  TransactionSignature,
+  VersionedTransaction,
+} from "@solana/web3.js";
+
+export interface KeeperConfig {
+  // Pull feeds to update in every transaction
+  feeds: PublicKey[];
+  // Oracle signatures requested per feed
+  numSignatures?: number;
+  // Optional gateway override
+  gateway?: string;
+  // Priority fee in micro-lamports per compute unit
+  computeUnitPrice?: number;
+  // Multiplier applied to the simulated compute units
+  computeUnitLimitMultiple?: number;
+}
+
+/**
+ * Reusable keeper that fetches signed oracle updates for a set of feeds and
+ * submits them together with the instructions of a consuming program.
+ */
+export class KeeperClient {
+  constructor(
+    readonly program: anchor.Program,
+    readonly connection: Connection,
+    readonly payer: Keypair,
+    readonly config: KeeperConfig
+  ) {}
+
+  /**
+   * Fetch the latest signed update for the configured feeds.
+   * Returns the update instruction and the lookup tables it needs.
+   */
+  async fetchUpdate(): Promise<
+    [TransactionInstruction, AddressLookupTableAccount[]]
+  > {
+    const [pullIx, luts] = await sb.PullFeed.fetchUpdateManyIx(this.program, {
+      gateway: this.config.gateway,
+      feeds: this.config.feeds,
+      numSignatures: this.config.numSignatures ?? 3,
+    });
+    return [pullIx, luts];
+  }
+
+  /**
+   * Build a transaction placing the feed update ahead of `ixs`, so the
+   * consuming program reads the freshly updated values.
+   */
+  async buildTx(ixs: TransactionInstruction[]): Promise<VersionedTransaction> {
+    const [pullIx, luts] = await this.fetchUpdate();
+    return sb.asV0Tx({
+      connection: this.connection,
+      ixs: [pullIx, ...ixs],
+      signers: [this.payer],
+      computeUnitPrice: this.config.computeUnitPrice ?? 200_000,
+      computeUnitLimitMultiple: this.config.computeUnitLimitMultiple ?? 1.3,
+      lookupTables: luts,
+    });
+  }
+
+  /**
+   * Update the feeds and submit `ixs` in a single transaction.
+   */
+  async submit(ixs: TransactionInstruction[]): Promise<TransactionSignature> {
+    const tx = await this.buildTx(ixs);
+    return this.connection.sendTransaction(tx);
+  }
+}
@@ -0,0 +1,81 @@
+import * as sb from "@switchboard-xyz/on-demand";
+import yargs from "yargs";
+import { myAnchorProgram } from "./utils";
+import { PublicKey } from "@solana/web3.js";
+
+const argv = yargs(process.argv).options({
+  feed: { type: "string", required: true },
+  program: {
+    type: "string",
+    default: "target/deploy/sb_on_demand_solana-keypair.json",
+    describe: "Keypair path of the program whose `test` instruction reads the feed",
+  },
+  dryRun: {
+    type: "boolean",
+    default: false,
+    describe: "Fetch the update but do not submit it",
+  },
+  pollMs: { type: "number", default: 400 },
+  timeoutMs: { type: "number", default: 60_000 },
+}).argv;
+
+async function resultSlot(feedAccount: sb.PullFeed): Promise<number> {
+  const data = await feedAccount.loadData();
+  return data.result.slot.toNumber();
+}
+
+(async function main() {
+  const { keypair, connection, program } = await sb.AnchorUtils.loadEnv();
+  const feed = new PublicKey(argv.feed);
+  const feedAccount = new sb.PullFeed(program, feed);
+  const demo = await myAnchorProgram(program.provider, argv.program).catch(
+    (e) => {
+      throw new Error("Failed to load the target program. Was it deployed?");
+    }
+  );
+  const startSlot = await resultSlot(feedAccount);
+
+  const fetchStart = Date.now();
+  const [pullIx, responses, success] = await feedAccount.fetchUpdateIx({
+    numSignatures: 3,
+  });
+  if (!success) throw new Error(`Errors: ${responses.map((x) => x.error)}`);
+  const fetchMs = Date.now() - fetchStart;
+  console.log(`Fetched oracle update in ${fetchMs}ms`);
+  if (argv.dryRun) {
+    console.log(`Dry run: not submitting. Current result slot: ${startSlot}`);
+    return;
+  }
+
+  const myIx = await demo.methods.test().accounts({ feed }).instruction();
+  const lutOwners = [...responses.map((x) => x.oracle), feedAccount];
+  const tx = await sb.asV0Tx({
+    connection,
+    ixs: [pullIx, myIx],
+    signers: [keypair],
+    computeUnitPrice: 200_000,
+    computeUnitLimitMultiple: 1.3,
+    lookupTables: await sb.loadLookupTables(lutOwners),
+  });
+
+  const submitStart = Date.now();
+  const sig = await connection.sendTransaction(tx);
+  console.log(`Transaction sent: ${sig}`);
+
+  // Poll until the feed's result slot moves past where it started.
+  while (Date.now() - submitStart < argv.timeoutMs) {
+    const slot = await resultSlot(feedAccount);
+    if (slot > startSlot) {
+      const landedMs = Date.now() - submitStart;
+      console.log(`Result slot ${startSlot} -> ${slot}`);
+      console.log(
+        `Latency: fetch ${fetchMs}ms + submit-to-fresh ${landedMs}ms = ${
+          fetchMs + landedMs
+        }ms`
+      );
+      return;
+    }
+    await sb.sleep(argv.pollMs);
+  }
+  throw new Error(`Feed not updated within ${argv.timeoutMs}ms`);
+})();
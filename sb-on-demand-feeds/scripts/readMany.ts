@@ -0,0 +1,76 @@
+import * as anchor from "@coral-xyz/anchor";
+import * as sb from "@switchboard-xyz/on-demand";
+import yargs from "yargs";
+import { fromScaled, myAnchorProgram } from "./utils";
+import { PublicKey } from "@solana/web3.js";
+
+const argv = yargs(process.argv).options({
+  feeds: { type: "array", string: true, required: true },
+  maxStaleSlots: { type: "number", default: 100 },
+}).argv;
+
+// Borsh layout of the `read_many` return value, Vec<FeedReading>:
+//   u32 (LE)       number of readings
+//   per reading:
+//     [u8; 32]     feed_id (the feed hash)
+//     i128 (LE)    value, 18 decimals, 0 if the feed was not supplied
+function decodeFeedReadings(data: Buffer): Array<[string, anchor.BN]> {
+  const count = data.readUInt32LE(0);
+  const readings: Array<[string, anchor.BN]> = [];
+  let offset = 4;
+  for (let i = 0; i < count; i++) {
+    const feedId = data.subarray(offset, offset + 32).toString("hex");
+    const value = new anchor.BN(data.subarray(offset + 32, offset + 48), "le")
+      .fromTwos(128);
+    readings.push([feedId, value]);
+    offset += 48;
+  }
+  return readings;
+}
+
+(async function main() {
+  const { keypair, connection, program } = await sb.AnchorUtils.loadEnv();
+  const feeds = argv.feeds.map((feed) => new PublicKey(feed));
+  const demoPath = "target/deploy/sb_on_demand_solana-keypair.json";
+  const demo = await myAnchorProgram(program.provider, demoPath).catch((e) => {
+    throw new Error("Failed to load demo program. Was it deployed?");
+  });
+
+  const feedIds: number[][] = [];
+  for (const feed of feeds) {
+    const data = await new sb.PullFeed(program, feed).loadData();
+    feedIds.push(Array.from(data.feedHash));
+  }
+  const myIx = await demo.methods
+    .readMany(feedIds, new anchor.BN(argv.maxStaleSlots))
+    .accounts({})
+    .remainingAccounts(
+      feeds.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false }))
+    )
+    .instruction();
+  const [pullIx, luts] = await sb.PullFeed.fetchUpdateManyIx(program, {
+    feeds,
+    numSignatures: 3,
+  });
+  const tx = await sb.asV0Tx({
+    connection,
+    ixs: [pullIx, myIx],
+    signers: [keypair],
+    computeUnitPrice: 200_000,
+    computeUnitLimitMultiple: 1.3,
+    lookupTables: luts,
+  });
+
+  // Return data is only set by the last instruction that calls
+  // set_return_data, which here is read_many.
+  const sim = await connection.simulateTransaction(tx, {
+    commitment: "processed",
+  });
+  if (sim.value.err) throw new Error(`Simulation failed: ${sim.value.err}`);
+  const [encoded] = sim.value.returnData.data;
+  for (const [feedId, value] of decodeFeedReadings(
+    Buffer.from(encoded, "base64")
+  )) {
+    console.log(`${feedId}: ${fromScaled(value)}`);
+  }
+})();
@@ -1,5 +1,6 @@
 import * as sb from "@switchboard-xyz/on-demand";
 import { myAnchorProgram } from "./utils";
+import { KeeperClient } from "./keeper";
 import { PublicKey } from "@solana/web3.js";
 
 (async function main() {
@@ -19,23 +20,14 @@ import { PublicKey } from "@solana/web3.js";
     .test()
     .accounts({ feed: feed2 })
     .instruction();
-  const conf = {
+  const keeper = new KeeperClient(program, connection, keypair, {
     gateway: "https://xoracle-1.switchboard.xyz",
     feeds: [feed1, feed2],
     numSignatures: 8,
-  };
+  });
 
   while (true) {
-    const [pullIx, luts] = await sb.PullFeed.fetchUpdateManyIx(program, conf);
-
-    const tx = await sb.asV0Tx({
-      connection,
-      ixs: [pullIx, myIx1, myIx2],
-      signers: [keypair],
-      computeUnitPrice: 200_000,
-      computeUnitLimitMultiple: 1.3,
-      lookupTables: luts,
-    });
+    const tx = await keeper.buildTx([myIx1, myIx2]);
 
     const sim = await connection.simulateTransaction(tx, { commitment });
     const sig = await connection.sendTransaction(tx);
@@ -1,13 +1,17 @@
 import { OracleJob } from "@switchboard-xyz/common";
 import * as anchor from "@coral-xyz/anchor";
 import {
+  ComputeBudgetProgram,
   Connection,
   Keypair,
   PublicKey,
+  TransactionInstruction,
+  TransactionMessage,
   VersionedTransaction,
   TransactionSignature,
 } from "@solana/web3.js";
 import * as sb from "@switchboard-xyz/on-demand";
+import Big from "big.js";
 
 export async function myAnchorProgram(
   provider: anchor.Provider,
@@ -20,6 +24,24 @@ export async function myAnchorProgram(
   return program;
 }
 
+/**
+ * Convert a decimal price into the program's fixed point representation with
+ * `decimals` decimal places (18 for feed values). Rounds half away from zero,
+ * matching `to_scaled` in the program crate.
+ */
+export function toScaled(price: number | string, decimals = 18): anchor.BN {
+  const scaled = new Big(price).times(new Big(10).pow(decimals)).round(0, Big.roundHalfUp);
+  return new anchor.BN(scaled.toFixed(0));
+}
+
+/**
+ * Convert a fixed point value with `decimals` decimal places back into a
+ * decimal string. Exact, unlike converting through a float.
+ */
+export function fromScaled(value: anchor.BN, decimals = 18): string {
+  return new Big(value.toString()).div(new Big(10).pow(decimals)).toString();
+}
+
 export function buildPythnetJob(pythFeed: string): OracleJob {
   const jobConfig = OracleJob.create({
     tasks: [
@@ -109,3 +131,50 @@ export async function sendAndConfirmTx(
   await connection.confirmTransaction(sig, "confirmed");
   return sig;
 }
+
+/**
+ * Build a signed transaction that updates `feeds` and reads them through the
+ * demo's `metered_read`. With `units` set, a compute budget instruction
+ * requesting that many compute units is prepended; without it the transaction
+ * runs under the runtime's default limit.
+ */
+export async function meteredReadTx(
+  program: anchor.Program,
+  demo: anchor.Program,
+  keypair: Keypair,
+  feeds: PublicKey[],
+  maxStaleSlots: number,
+  units?: number
+): Promise<VersionedTransaction> {
+  const connection = program.provider.connection;
+  const myIx = await demo.methods
+    .meteredRead(new anchor.BN(maxStaleSlots))
+    .accounts({})
+    .remainingAccounts(
+      feeds.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false }))
+    )
+    .instruction();
+  const [pullIx, luts] = await sb.PullFeed.fetchUpdateManyIx(program, {
+    feeds,
+    numSignatures: 3,
+  });
+
+  const instructions: TransactionInstruction[] = [];
+  if (units !== undefined) {
+    instructions.push(ComputeBudgetProgram.setComputeUnitLimit({ units }));
+  }
+  instructions.push(
+    ComputeBudgetProgram.setComputeUnitPrice({ microLamports: 200_000 }),
+    pullIx,
+    myIx
+  );
+  const { blockhash } = await connection.getLatestBlockhash();
+  const message = new TransactionMessage({
+    payerKey: keypair.publicKey,
+    recentBlockhash: blockhash,
+    instructions,
+  }).compileToV0Message(luts);
+  const tx = new VersionedTransaction(message);
+  tx.sign([keypair]);
+  return tx;
+}
@@ -0,0 +1,79 @@
+import * as sb from "@switchboard-xyz/on-demand";
+import { assert } from "chai";
+import { PublicKey } from "@solana/web3.js";
+import { meteredReadTx, myAnchorProgram } from "../scripts/utils";
+
+// Runs against the cluster of the loaded Solana env with the demo deployed.
+// FEEDS is a comma separated list of pull feeds; pass enough of them that
+// updating and reading them all needs more than the runtime's default limit.
+const FEEDS = (process.env.FEEDS ?? "").split(",").filter((feed) => feed);
+const MAX_STALE_SLOTS = 100;
+const RAISED_UNITS = 1_400_000;
+// Default limit per instruction that is not a compute budget instruction.
+const DEFAULT_UNITS_PER_IX = 200_000;
+
+describe("metered_read compute budget", function () {
+  let env: Awaited<ReturnType<typeof sb.AnchorUtils.loadEnv>>;
+  let demo: Awaited<ReturnType<typeof myAnchorProgram>>;
+  let feeds: PublicKey[];
+
+  before(async function () {
+    if (FEEDS.length === 0) this.skip();
+    env = await sb.AnchorUtils.loadEnv();
+    demo = await myAnchorProgram(
+      env.program.provider,
+      "target/deploy/sb_on_demand_solana-keypair.json"
+    );
+    feeds = FEEDS.map((feed) => new PublicKey(feed));
+  });
+
+  async function simulate(units?: number) {
+    const tx = await meteredReadTx(
+      env.program,
+      demo,
+      env.keypair,
+      feeds,
+      MAX_STALE_SLOTS,
+      units
+    );
+    const sim = await env.connection.simulateTransaction(tx);
+    return sim.value;
+  }
+
+  function assertOutOfCompute(result: Awaited<ReturnType<typeof simulate>>) {
+    assert.isNotNull(result.err);
+    assert.isTrue(
+      (result.logs ?? []).some((log) =>
+        /exceeded CUs meter|ComputationalBudgetExceeded/.test(log)
+      ),
+      "expected the transaction to run out of compute units"
+    );
+  }
+
+  it("succeeds with the raised limit and fails without it", async function () {
+    const raised = await simulate(RAISED_UNITS);
+    assert.isNull(raised.err, JSON.stringify(raised.logs));
+    // The handler logs the compute units left before and after its reads.
+    const consumption = (raised.logs ?? []).filter((log) =>
+      log.includes("consumption")
+    );
+    assert.isAtLeast(consumption.length, 2);
+
+    // Without a compute budget instruction the update and the read share
+    // the default limit of the two instructions.
+    const defaultUnits = 2 * DEFAULT_UNITS_PER_IX;
+    if (raised.unitsConsumed! <= defaultUnits) {
+      console.log(`${raised.unitsConsumed} units fit the default; add FEEDS`);
+      this.skip();
+    }
+    assertOutOfCompute(await simulate());
+  });
+
+  it("fails with a limit below the measured usage", async () => {
+    const raised = await simulate(RAISED_UNITS);
+    assert.isNull(raised.err, JSON.stringify(raised.logs));
+    // Each simulation fetches a fresh update, so leave room for small
+    // differences in the units consumed between the two.
+    assertOutOfCompute(await simulate(Math.floor(raised.unitsConsumed! / 2)));
+  });
+});
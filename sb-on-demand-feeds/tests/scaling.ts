@@ -0,0 +1,34 @@
+import * as anchor from "@coral-xyz/anchor";
+import { assert } from "chai";
+import { fromScaled, toScaled } from "../scripts/utils";
+
+describe("toScaled / fromScaled", () => {
+  it("scales to 18 decimals by default", () => {
+    assert.equal(toScaled("1.5").toString(), "1500000000000000000");
+    assert.equal(toScaled(60000).toString(), "60000" + "0".repeat(18));
+    assert.equal(toScaled("-1.5", 6).toString(), "-1500000");
+  });
+
+  it("rounds half away from zero", () => {
+    assert.equal(toScaled("2.5", 0).toString(), "3");
+    assert.equal(toScaled("-2.5", 0).toString(), "-3");
+    assert.equal(toScaled("0.0000005", 6).toString(), "1");
+  });
+
+  it("round-trips exactly", () => {
+    for (const value of ["0", "1", "-2.25", "0.000001", "3000.123456", "60000.987654321"]) {
+      assert.equal(fromScaled(toScaled(value)), value);
+      assert.equal(fromScaled(toScaled(value, 9), 9), value);
+    }
+  });
+
+  it("handles values beyond i128 and float precision", () => {
+    // 2^127 - 1, the largest value the program's i128 can hold.
+    const i128Max = new anchor.BN(2).pow(new anchor.BN(127)).subn(1);
+    assert.equal(toScaled(fromScaled(i128Max)).toString(), i128Max.toString());
+    // Values too large for the program are still converted exactly; callers
+    // must range-check before sending them on chain.
+    assert.equal(toScaled("1e30").toString(), "1" + "0".repeat(48));
+    assert.isTrue(toScaled("1e30").gt(i128Max));
+  });
+});
@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
+use std::cell::Ref;
 use switchboard_on_demand::accounts::RandomnessAccountData;
+use switchboard_on_demand::program_id::{ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
 
 declare_id!("9kVUcr3z7PTRdSfByhB1ud1Xivcm8ZkuM9vkRfH6PCof");
 
@@ -29,6 +31,190 @@ pub fn transfer<'a>(
     anchor_lang::system_program::transfer(transfer_ctx, amount)
 }
 
+/// Parse a randomness account, rejecting accounts not owned by Switchboard
+/// and accounts too short to hold one instead of letting the SDK panic on
+/// truncated data. The SDK only checks the discriminator, so without the
+/// owner check anyone could forge an account with a chosen value and queue.
+pub fn load_randomness<'a>(account: &'a AccountInfo) -> Result<Ref<'a, RandomnessAccountData>> {
+    if account.owner != &ON_DEMAND_MAINNET_PID && account.owner != &ON_DEMAND_DEVNET_PID {
+        msg!("Randomness account {} is owned by {}", account.key(), account.owner);
+        return Err(ErrorCode::InvalidRandomnessOwner.into());
+    }
+    let data = account.data.borrow();
+    let min_len = 8 + std::mem::size_of::<RandomnessAccountData>();
+    if data.len() < min_len {
+        msg!("Randomness account holds {} bytes, expected at least {}", data.len(), min_len);
+        return Err(ErrorCode::InvalidRandomnessAccount.into());
+    }
+    RandomnessAccountData::parse(data).map_err(|e| {
+        msg!("Parse Error: {:?}", e);
+        ErrorCode::InvalidRandomnessAccount.into()
+    })
+}
+
+/// Mix a revealed Switchboard value with the most recent entry of the
+/// SlotHashes sysvar: `sha256(revealed_value || slot_hash)`.
+/// The result is only predictable to someone who controls both the
+/// randomness oracle and the slot leader.
+pub fn mix_with_slot_hash(revealed_value: &[u8; 32], slot_hash: &[u8; 32]) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[revealed_value, slot_hash]).to_bytes()
+}
+
+/// Read the most recent hash out of the SlotHashes sysvar without
+/// deserializing the whole (large) account.
+/// Layout: u64 entry count, followed by `(u64 slot, [u8; 32] hash)` entries, newest first.
+pub fn latest_slot_hash(slot_hashes: &AccountInfo) -> Result<[u8; 32]> {
+    let data = slot_hashes.try_borrow_data()?;
+    if data.len() < 8 + 8 + 32 {
+        msg!("SlotHashes holds {} bytes, no entry to mix with", data.len());
+        // UNSAFE, test only: local validators may not populate SlotHashes.
+        // Mixing with a zero hash adds no entropy, so never enable this in
+        // production; every other build fails closed.
+        if cfg!(feature = "allow-missing-slothash") {
+            msg!("UNSAFE: allow-missing-slothash is enabled, mixing with a zero hash");
+            return Ok([0u8; 32]);
+        }
+        return Err(ErrorCode::SlotHashesUnavailable.into());
+    }
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&data[16..48]);
+    Ok(hash)
+}
+
+// Queues whose randomness this program accepts: the mainnet and devnet default queues.
+pub const ALLOWED_RANDOMNESS_QUEUES: &[Pubkey] = &[
+    anchor_lang::solana_program::pubkey!("A43DyUGA7s8eXPxqEjJY6EBu1KKbNgfxF8h17VAHn13w"),
+    anchor_lang::solana_program::pubkey!("FfD96yeXs4cxZshoPPSKhSPgVQxLAJUT3gefgh84m1Di"),
+];
+
+/// Reject randomness produced by a queue outside ALLOWED_RANDOMNESS_QUEUES.
+pub fn check_randomness_queue(queue: &Pubkey) -> Result<()> {
+    if !ALLOWED_RANDOMNESS_QUEUES.contains(queue) {
+        msg!("randomness queue {} is not approved", queue);
+        return Err(ErrorCode::InvalidRandomnessQueue.into());
+    }
+    Ok(())
+}
+
+/// Outcome of a coin flip for `user`: heads (true) when the first byte of
+/// `sha256(revealed_value || user)` is even. Mixing in the player keeps
+/// players who share a randomness account independent, and settle_flip and
+/// settle_batch both use it so neither path can be chosen for a better result.
+pub fn flip_outcome(revealed_value: &[u8; 32], user: &Pubkey) -> bool {
+    let player_value = anchor_lang::solana_program::hash::hashv(&[revealed_value, user.as_ref()]).to_bytes();
+    player_value[0] % 2 == 0
+}
+
+/// Payouts are system transfers, which only work from a system-owned escrow.
+pub fn check_escrow_owner(escrow: &AccountInfo) -> Result<()> {
+    require_keys_eq!(*escrow.owner, anchor_lang::system_program::ID, ErrorCode::InvalidEscrowOwner);
+    Ok(())
+}
+
+/// Reveal the randomness a player committed to in coin_flip. Checks that
+/// `randomness_info` is the committed account and comes from a pinned queue,
+/// then clears the commitment so the same flip cannot be settled twice.
+pub fn reveal_committed(
+    player_state: &mut PlayerState,
+    randomness_info: &AccountInfo,
+    clock: &Clock,
+) -> Result<[u8; 32]> {
+    require_keys_eq!(randomness_info.key(), player_state.randomness_account, ErrorCode::Unauthorized);
+    let randomness_data = load_randomness(randomness_info)?;
+    // The queue account itself is not needed: the randomness account
+    // records its queue, which must be one of the pinned queues.
+    check_randomness_queue(&randomness_data.queue)?;
+    let revealed_random_value = randomness_data.get_value(clock)
+        .map_err(|_| ErrorCode::RandomnessNotResolved)?;
+    player_state.randomness_account = Pubkey::default();
+    Ok(revealed_random_value)
+}
+
+// Loot box probability weights are expressed in basis points of this total.
+pub const LOOTBOX_WEIGHT_TOTAL: u16 = 10_000;
+
+/// Map a revealed random value onto a rarity tier. `weights` holds the basis
+/// point share of Common, Rare, Epic and Legendary and must sum to LOOTBOX_WEIGHT_TOTAL.
+pub fn rarity_from_randomness(value: &[u8; 32], weights: &[u16; 4]) -> Rarity {
+    let mut roll_bytes = [0u8; 8];
+    roll_bytes.copy_from_slice(&value[..8]);
+    let roll = (u64::from_le_bytes(roll_bytes) % LOOTBOX_WEIGHT_TOTAL as u64) as u16;
+    let tiers = [Rarity::Common, Rarity::Rare, Rarity::Epic, Rarity::Legendary];
+    let mut upper = 0u16;
+    for (tier, weight) in tiers.iter().zip(weights.iter()) {
+        upper += weight;
+        if roll < upper {
+            return *tier;
+        }
+    }
+    Rarity::Legendary
+}
+
+/// Require randomness to have been committed in the previous slot, telling
+/// apart randomness that is too old from randomness seeded ahead of the clock.
+pub fn check_seed_slot(seed_slot: u64, clock: &Clock) -> Result<()> {
+    let expected_slot = clock.slot.saturating_sub(1);
+    if seed_slot == expected_slot {
+        return Ok(());
+    }
+    msg!("seed_slot: {}", seed_slot);
+    msg!("slot: {}", clock.slot);
+    if seed_slot > expected_slot {
+        return Err(ErrorCode::RandomnessFromFutureSlot.into());
+    }
+    Err(ErrorCode::RandomnessAlreadyRevealed.into())
+}
+
+// Side length of the square tile grid generated by generate_map.
+pub const MAP_SIZE: usize = 8;
+
+// Cumulative percentage thresholds for Water, Grass, Forest and Mountain tiles.
+pub const TILE_THRESHOLDS: [u8; 4] = [25, 70, 90, 100];
+
+/// Deterministically expand a revealed random value into a MAP_SIZE x MAP_SIZE
+/// grid. Each tile hashes the seed with its index, so the same seed always
+/// reproduces the same map.
+pub fn generate_tiles(seed: &[u8; 32]) -> [TileType; MAP_SIZE * MAP_SIZE] {
+    let mut tiles = [TileType::Water; MAP_SIZE * MAP_SIZE];
+    for (index, tile) in tiles.iter_mut().enumerate() {
+        let hash = anchor_lang::solana_program::hash::hashv(&[seed, &(index as u16).to_le_bytes()]);
+        let mut roll_bytes = [0u8; 8];
+        roll_bytes.copy_from_slice(&hash.to_bytes()[..8]);
+        let roll = (u64::from_le_bytes(roll_bytes) % 100) as u8;
+        let kinds = [TileType::Water, TileType::Grass, TileType::Forest, TileType::Mountain];
+        *tile = kinds
+            .iter()
+            .zip(TILE_THRESHOLDS.iter())
+            .find(|(_, upper)| roll < **upper)
+            .map(|(kind, _)| *kind)
+            .unwrap_or(TileType::Mountain);
+    }
+    tiles
+}
+
+// Maximum number of players waiting in a MatchQueue.
+pub const MAX_QUEUE_PLAYERS: usize = 16;
+
+/// Shuffle `players` with a Fisher-Yates shuffle driven by `seed` and pair them
+/// off in order. With an odd count the last shuffled player is left unmatched.
+/// The same seed and player list always produce the same pairings.
+pub fn pair_players(seed: &[u8; 32], players: &[Pubkey]) -> (Vec<MatchPair>, Option<Pubkey>) {
+    let mut shuffled = players.to_vec();
+    for i in (1..shuffled.len()).rev() {
+        let hash = anchor_lang::solana_program::hash::hashv(&[seed, &(i as u16).to_le_bytes()]);
+        let mut roll_bytes = [0u8; 8];
+        roll_bytes.copy_from_slice(&hash.to_bytes()[..8]);
+        let j = (u64::from_le_bytes(roll_bytes) % (i as u64 + 1)) as usize;
+        shuffled.swap(i, j);
+    }
+    let pairs = shuffled
+        .chunks_exact(2)
+        .map(|pair| MatchPair { player_a: pair[0], player_b: pair[1] })
+        .collect();
+    let unmatched = if shuffled.len() % 2 == 1 { shuffled.last().copied() } else { None };
+    (pairs, unmatched)
+}
+
 #[program]
 pub mod sb_randomness {
     use super::*;
@@ -50,13 +236,18 @@ pub mod sb_randomness {
         let player_state = &mut ctx.accounts.player_state;
         // Record the user's guess
         player_state.current_guess = guess;
-        let randomness_data = RandomnessAccountData::parse(ctx.accounts.randomness_account_data.data.borrow()).unwrap();
+        // The stored key must be the account whose seed slot and queue are checked
+        // here, otherwise settle_flip would reveal unvalidated randomness.
+        require_keys_eq!(
+            randomness_account,
+            ctx.accounts.randomness_account_data.key(),
+            ErrorCode::RandomnessAccountMismatch
+        );
+        let randomness_data = load_randomness(&ctx.accounts.randomness_account_data)?;
 
-        if randomness_data.seed_slot != clock.slot - 1 {
-            msg!("seed_slot: {}", randomness_data.seed_slot);
-            msg!("slot: {}", clock.slot);
-            return Err(ErrorCode::RandomnessAlreadyRevealed.into());
-        }
+        // Only accept randomness produced by one of the pinned queues
+        check_randomness_queue(&randomness_data.queue)?;
+        check_seed_slot(randomness_data.seed_slot, &clock)?;
         // ***
         // IMPORTANT: Remember, in Switchboard Randomness, it's the responsibility of the caller to reveal the randomness.
         // Therefore, the game collateral MUST be taken upon randomness request, not on reveal.
@@ -79,17 +270,20 @@ pub mod sb_randomness {
 
     // Settle the flip after randomness is revealed
     pub fn settle_flip(ctx: Context<SettleFlip>, escrow_bump: u8) -> Result<()> {
+        // A wrong bump would otherwise surface as an opaque CPI signer failure.
+        if escrow_bump != ctx.bumps.escrow_account {
+            msg!("escrow_bump: {}, expected: {}", escrow_bump, ctx.bumps.escrow_account);
+            return Err(ErrorCode::InvalidEscrowBump.into());
+        }
 
         let clock: Clock = Clock::get()?;
         let player_state = &mut ctx.accounts.player_state;
-        // call the switchboard on-demand parse function to get the randomness data
-        let randomness_data = RandomnessAccountData::parse(ctx.accounts.randomness_account_data.data.borrow()).unwrap();
-        // call the switchboard on-demand get_value function to get the revealed random value
-        let revealed_random_value = randomness_data.get_value(&clock)
-            .map_err(|_| ErrorCode::RandomnessNotResolved)?;
+        // Only the randomness committed to in coin_flip may settle the flip.
+        let revealed_random_value =
+            reveal_committed(player_state, &ctx.accounts.randomness_account_data, &clock)?;
 
-        // Use the revealed random value to determine the flip results
-        let randomness_result = revealed_random_value[0] % 2 == 0;
+        // Use the revealed random value to determine the flip results.
+        let randomness_result = flip_outcome(&revealed_random_value, &ctx.accounts.user.key());
 
         // Update and log the result
         player_state.latest_flip_result = randomness_result;
@@ -107,6 +301,7 @@ pub mod sb_randomness {
         }
         if randomness_result == player_state.current_guess {
             msg!("You win!");
+            check_escrow_owner(&ctx.accounts.escrow_account)?;
             let rent = Rent::get()?;
             let needed_lamports = player_state.wager * 2 + rent.minimum_balance(ctx.accounts.escrow_account.data_len());
             if needed_lamports > ctx.accounts.escrow_account.lamports() {
@@ -128,6 +323,350 @@ pub mod sb_randomness {
 
         Ok(())
     }
+
+    // Derive a final random value from the revealed randomness and the
+    // latest slot hash, for callers who want an extra source of entropy.
+    pub fn mixed_randomness(ctx: Context<MixedRandomness>) -> Result<[u8; 32]> {
+        let clock: Clock = Clock::get()?;
+        let randomness_data = load_randomness(&ctx.accounts.randomness_account_data)?;
+        check_randomness_queue(&randomness_data.queue)?;
+        let revealed_random_value = randomness_data.get_value(&clock)
+            .map_err(|_| ErrorCode::RandomnessNotResolved)?;
+        let slot_hash = latest_slot_hash(&ctx.accounts.slot_hashes)?;
+        let mixed = mix_with_slot_hash(&revealed_random_value, &slot_hash);
+        msg!("MIXED_RANDOMNESS: {:?}", mixed);
+        Ok(mixed)
+    }
+
+    // Configure the probability of each loot box rarity, in basis points.
+    pub fn init_lootbox(ctx: Context<InitLootbox>, weights: [u16; 4]) -> Result<()> {
+        let total: u32 = weights.iter().map(|w| *w as u32).sum();
+        if total != LOOTBOX_WEIGHT_TOTAL as u32 {
+            msg!("Weights sum to {}, expected {}", total, LOOTBOX_WEIGHT_TOTAL);
+            return Err(ErrorCode::InvalidLootboxWeights.into());
+        }
+        let config = &mut ctx.accounts.lootbox_config;
+        config.authority = ctx.accounts.authority.key();
+        config.weights = weights;
+        config.bump = ctx.bumps.lootbox_config;
+        Ok(())
+    }
+
+    pub fn init_lootbox_ticket(ctx: Context<InitLootboxTicket>) -> Result<()> {
+        let ticket = &mut ctx.accounts.ticket;
+        ticket.user = ctx.accounts.user.key();
+        ticket.randomness_account = Pubkey::default();
+        ticket.opened = true;
+        ticket.bump = ctx.bumps.ticket;
+        Ok(())
+    }
+
+    // Commit to a randomness account before it is revealed, as in coin_flip.
+    pub fn commit_lootbox(ctx: Context<CommitLootbox>, randomness_account: Pubkey) -> Result<()> {
+        let clock = Clock::get()?;
+        let ticket = &mut ctx.accounts.ticket;
+        require!(ticket.opened, ErrorCode::GameStillActive);
+        // The stored key must be the account whose seed slot is checked here.
+        require_keys_eq!(
+            randomness_account,
+            ctx.accounts.randomness_account_data.key(),
+            ErrorCode::RandomnessAccountMismatch
+        );
+        let randomness_data = load_randomness(&ctx.accounts.randomness_account_data)?;
+        check_randomness_queue(&randomness_data.queue)?;
+        check_seed_slot(randomness_data.seed_slot, &clock)?;
+        ticket.randomness_account = randomness_account;
+        ticket.opened = false;
+        msg!("Loot box committed, randomness requested.");
+        Ok(())
+    }
+
+    // Open the committed loot box once its randomness has been revealed.
+    pub fn open_lootbox(ctx: Context<OpenLootbox>) -> Result<Rarity> {
+        let clock: Clock = Clock::get()?;
+        let ticket = &mut ctx.accounts.ticket;
+        require!(!ticket.opened, ErrorCode::RandomnessNotResolved);
+        require_keys_eq!(
+            ctx.accounts.randomness_account_data.key(),
+            ticket.randomness_account,
+            ErrorCode::Unauthorized
+        );
+        let randomness_data = load_randomness(&ctx.accounts.randomness_account_data)?;
+        check_randomness_queue(&randomness_data.queue)?;
+        let revealed_random_value = randomness_data.get_value(&clock)
+            .map_err(|_| ErrorCode::RandomnessNotResolved)?;
+
+        let rarity = rarity_from_randomness(&revealed_random_value, &ctx.accounts.lootbox_config.weights);
+        ticket.opened = true;
+        ticket.last_rarity = rarity;
+        msg!("LOOTBOX_RESULT: {:?}", rarity);
+        Ok(rarity)
+    }
+
+    pub fn init_map(ctx: Context<InitMap>) -> Result<()> {
+        let map = &mut ctx.accounts.map;
+        map.user = ctx.accounts.user.key();
+        map.randomness_account = Pubkey::default();
+        map.generated = true;
+        map.bump = ctx.bumps.map;
+        Ok(())
+    }
+
+    // Commit to a randomness account for the next map, as in coin_flip.
+    pub fn commit_map(ctx: Context<CommitMap>, randomness_account: Pubkey) -> Result<()> {
+        let clock = Clock::get()?;
+        let map = &mut ctx.accounts.map;
+        require!(map.generated, ErrorCode::GameStillActive);
+        // The stored key must be the account whose seed slot is checked here.
+        require_keys_eq!(
+            randomness_account,
+            ctx.accounts.randomness_account_data.key(),
+            ErrorCode::RandomnessAccountMismatch
+        );
+        let randomness_data = load_randomness(&ctx.accounts.randomness_account_data)?;
+        check_randomness_queue(&randomness_data.queue)?;
+        check_seed_slot(randomness_data.seed_slot, &clock)?;
+        map.randomness_account = randomness_account;
+        map.generated = false;
+        msg!("Map committed, randomness requested.");
+        Ok(())
+    }
+
+    // Generate the tile grid from the revealed randomness and store it with its seed.
+    pub fn generate_map(ctx: Context<GenerateMap>) -> Result<()> {
+        let clock: Clock = Clock::get()?;
+        let map = &mut ctx.accounts.map;
+        require!(!map.generated, ErrorCode::RandomnessNotResolved);
+        require_keys_eq!(
+            ctx.accounts.randomness_account_data.key(),
+            map.randomness_account,
+            ErrorCode::Unauthorized
+        );
+        let randomness_data = load_randomness(&ctx.accounts.randomness_account_data)?;
+        check_randomness_queue(&randomness_data.queue)?;
+        let revealed_random_value = randomness_data.get_value(&clock)
+            .map_err(|_| ErrorCode::RandomnessNotResolved)?;
+
+        map.seed = revealed_random_value;
+        map.tiles = generate_tiles(&revealed_random_value);
+        map.generated = true;
+        msg!("MAP_SEED: {:?}", revealed_random_value);
+        Ok(())
+    }
+
+    // Settle many flips in one transaction. remaining_accounts holds one
+    // (player_state, randomness_account, user) triple per player; players may
+    // share a randomness account. Each flip pays out exactly as in settle_flip.
+    pub fn settle_batch<'info>(ctx: Context<'_, '_, 'info, 'info, SettleBatch<'info>>) -> Result<()> {
+        let clock: Clock = Clock::get()?;
+        let accounts = ctx.remaining_accounts;
+        require!(!accounts.is_empty() && accounts.len() % 3 == 0, ErrorCode::InvalidBatchAccounts);
+        check_escrow_owner(&ctx.accounts.escrow_account)?;
+        let escrow_bump = ctx.bumps.escrow_account;
+        let seed_prefix = b"stateEscrow".as_ref();
+        let escrow_seed = &[&seed_prefix[..], &[escrow_bump]];
+        let seeds_slice: &[&[u8]] = escrow_seed;
+        let binding = [seeds_slice];
+
+        for entry in accounts.chunks(3) {
+            let (state_info, randomness_info, user_info) = (&entry[0], &entry[1], &entry[2]);
+            let mut player_state = Account::<PlayerState>::try_from(state_info)?;
+            let (expected_state, _) = Pubkey::find_program_address(
+                &[b"playerState".as_ref(), user_info.key.as_ref()],
+                ctx.program_id,
+            );
+            require_keys_eq!(state_info.key(), expected_state, ErrorCode::InvalidBatchAccounts);
+            require_keys_eq!(user_info.key(), player_state.allowed_user, ErrorCode::Unauthorized);
+            // Same checks as settle_flip: committed account, pinned queue, revealed value.
+            let revealed_random_value = reveal_committed(&mut player_state, randomness_info, &clock)?;
+            let randomness_result = flip_outcome(&revealed_random_value, user_info.key);
+            player_state.latest_flip_result = randomness_result;
+
+            if randomness_result == player_state.current_guess {
+                msg!("{}: win", user_info.key());
+                let rent = Rent::get()?;
+                let needed_lamports = player_state.wager * 2 + rent.minimum_balance(ctx.accounts.escrow_account.data_len());
+                if needed_lamports > ctx.accounts.escrow_account.lamports() {
+                    msg!("Not enough funds in treasury to pay out {}", user_info.key());
+                } else {
+                    transfer(
+                        ctx.accounts.system_program.to_account_info(),
+                        ctx.accounts.escrow_account.to_account_info(),
+                        user_info.clone(),
+                        player_state.wager * 2,
+                        Some(&binding),
+                    )?;
+                }
+            } else {
+                msg!("{}: lose", user_info.key());
+            }
+            player_state.exit(ctx.program_id)?;
+        }
+        msg!("Settled {} flips", accounts.len() / 3);
+        Ok(())
+    }
+
+    pub fn init_match_queue(ctx: Context<InitMatchQueue>) -> Result<()> {
+        let queue = &mut ctx.accounts.match_queue;
+        queue.authority = ctx.accounts.authority.key();
+        queue.randomness_account = Pubkey::default();
+        queue.committed = false;
+        queue.bump = ctx.bumps.match_queue;
+        Ok(())
+    }
+
+    // Join the waiting queue. Joining is closed once randomness is committed,
+    // so the player set cannot change after the seed is chosen.
+    pub fn join_queue(ctx: Context<JoinQueue>) -> Result<()> {
+        let queue = &mut ctx.accounts.match_queue;
+        require!(!queue.committed, ErrorCode::GameStillActive);
+        let player = ctx.accounts.player.key();
+        require!(!queue.players.contains(&player), ErrorCode::AlreadyQueued);
+        require!(queue.players.len() < MAX_QUEUE_PLAYERS, ErrorCode::QueueFull);
+        queue.players.push(player);
+        Ok(())
+    }
+
+    // Commit to a randomness account for the next round of matches.
+    pub fn commit_matchmaking(ctx: Context<CommitMatchmaking>, randomness_account: Pubkey) -> Result<()> {
+        let clock = Clock::get()?;
+        let queue = &mut ctx.accounts.match_queue;
+        require!(!queue.committed, ErrorCode::GameStillActive);
+        // The stored key must be the account whose seed slot is checked here.
+        require_keys_eq!(
+            randomness_account,
+            ctx.accounts.randomness_account_data.key(),
+            ErrorCode::RandomnessAccountMismatch
+        );
+        let randomness_data = load_randomness(&ctx.accounts.randomness_account_data)?;
+        check_randomness_queue(&randomness_data.queue)?;
+        check_seed_slot(randomness_data.seed_slot, &clock)?;
+        queue.randomness_account = randomness_account;
+        queue.committed = true;
+        msg!("Matchmaking committed for {} players.", queue.players.len());
+        Ok(())
+    }
+
+    // Pair the waiting players using the revealed randomness and empty the queue.
+    pub fn matchmake(ctx: Context<Matchmake>) -> Result<()> {
+        let clock: Clock = Clock::get()?;
+        let queue = &mut ctx.accounts.match_queue;
+        require!(queue.committed, ErrorCode::RandomnessNotResolved);
+        require_keys_eq!(
+            ctx.accounts.randomness_account_data.key(),
+            queue.randomness_account,
+            ErrorCode::Unauthorized
+        );
+        let randomness_data = load_randomness(&ctx.accounts.randomness_account_data)?;
+        check_randomness_queue(&randomness_data.queue)?;
+        let revealed_random_value = randomness_data.get_value(&clock)
+            .map_err(|_| ErrorCode::RandomnessNotResolved)?;
+
+        let (pairs, unmatched) = pair_players(&revealed_random_value, &queue.players);
+        for pair in pairs.iter() {
+            msg!("MATCH: {} vs {}", pair.player_a, pair.player_b);
+        }
+        if let Some(player) = unmatched {
+            msg!("UNMATCHED: {}", player);
+        }
+        queue.pairs = pairs;
+        queue.unmatched = unmatched;
+        queue.players.clear();
+        queue.committed = false;
+        Ok(())
+    }
+
+    pub fn init_dice(ctx: Context<InitDice>) -> Result<()> {
+        let game = &mut ctx.accounts.dice_game;
+        game.user = ctx.accounts.user.key();
+        game.randomness_account = Pubkey::default();
+        game.rolled = true;
+        game.bump = ctx.bumps.dice_game;
+        Ok(())
+    }
+
+    // Commit to a randomness account (the server seed) together with
+    // `sha256(client_seed)`, before either is known to the other party.
+    pub fn commit_dice(
+        ctx: Context<CommitDice>,
+        randomness_account: Pubkey,
+        client_seed_hash: [u8; 32],
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let game = &mut ctx.accounts.dice_game;
+        require!(game.rolled, ErrorCode::GameStillActive);
+        // The stored key must be the account whose seed slot is checked here.
+        require_keys_eq!(
+            randomness_account,
+            ctx.accounts.randomness_account_data.key(),
+            ErrorCode::RandomnessAccountMismatch
+        );
+        let randomness_data = load_randomness(&ctx.accounts.randomness_account_data)?;
+        check_randomness_queue(&randomness_data.queue)?;
+        check_seed_slot(randomness_data.seed_slot, &clock)?;
+        game.randomness_account = randomness_account;
+        game.client_seed_hash = client_seed_hash;
+        game.rolled = false;
+        msg!("Dice committed, randomness requested.");
+        Ok(())
+    }
+
+    // Reveal the client seed and roll: `sha256(server_seed || client_seed)`
+    // mapped onto 1..=6. Anyone can recompute the roll from the logged seeds.
+    pub fn roll_dice(ctx: Context<RollDice>, client_seed: [u8; 32]) -> Result<u8> {
+        let clock: Clock = Clock::get()?;
+        let game = &mut ctx.accounts.dice_game;
+        require!(!game.rolled, ErrorCode::RandomnessNotResolved);
+        require_keys_eq!(
+            ctx.accounts.randomness_account_data.key(),
+            game.randomness_account,
+            ErrorCode::Unauthorized
+        );
+        let revealed_hash = anchor_lang::solana_program::hash::hash(&client_seed).to_bytes();
+        if revealed_hash != game.client_seed_hash {
+            msg!("Revealed client seed does not match the commitment");
+            return Err(ErrorCode::ClientSeedMismatch.into());
+        }
+        let randomness_data = load_randomness(&ctx.accounts.randomness_account_data)?;
+        check_randomness_queue(&randomness_data.queue)?;
+        let server_seed = randomness_data.get_value(&clock)
+            .map_err(|_| ErrorCode::RandomnessNotResolved)?;
+
+        let combined = anchor_lang::solana_program::hash::hashv(&[&server_seed, &client_seed]).to_bytes();
+        let mut roll_bytes = [0u8; 8];
+        roll_bytes.copy_from_slice(&combined[..8]);
+        let roll = (u64::from_le_bytes(roll_bytes) % 6) as u8 + 1;
+        game.rolled = true;
+        game.last_roll = roll;
+        msg!("SERVER_SEED: {:?}", server_seed);
+        msg!("CLIENT_SEED: {:?}", client_seed);
+        msg!("DICE_RESULT: {}", roll);
+        Ok(roll)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TileType {
+    #[default]
+    Water,
+    Grass,
+    Forest,
+    Mountain,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MatchPair {
+    pub player_a: Pubkey,
+    pub player_b: Pubkey,
 }
 
 // === Accounts ===
@@ -141,6 +680,53 @@ pub struct PlayerState {
     bump: u8,
 }
 
+#[account]
+pub struct LootboxConfig {
+    authority: Pubkey,
+    weights: [u16; 4], // Basis points for Common, Rare, Epic, Legendary
+    bump: u8,
+}
+
+#[account]
+pub struct LootboxTicket {
+    user: Pubkey,
+    randomness_account: Pubkey, // Randomness committed to for the pending loot box
+    opened: bool,
+    last_rarity: Rarity, // Result of the most recently opened loot box
+    bump: u8,
+}
+
+#[account]
+pub struct GameMap {
+    user: Pubkey,
+    randomness_account: Pubkey, // Randomness committed to for the pending map
+    generated: bool,
+    seed: [u8; 32], // Revealed value the current tiles were generated from
+    tiles: [TileType; MAP_SIZE * MAP_SIZE], // Row-major tile grid
+    bump: u8,
+}
+
+#[account]
+pub struct MatchQueue {
+    authority: Pubkey,
+    randomness_account: Pubkey, // Randomness committed to for the pending round
+    committed: bool,
+    players: Vec<Pubkey>, // Players waiting for the next round
+    pairs: Vec<MatchPair>, // Pairings from the last round
+    unmatched: Option<Pubkey>, // Player left over from the last round, if any
+    bump: u8,
+}
+
+#[account]
+pub struct DiceGame {
+    user: Pubkey,
+    randomness_account: Pubkey, // Server seed committed to for the pending roll
+    client_seed_hash: [u8; 32], // sha256 of the player's client seed
+    rolled: bool,
+    last_roll: u8, // 1 to 6
+    bump: u8,
+}
+
 // === Instructions ===
 #[derive(Accounts)]
 pub struct Initialize<'info> {
@@ -185,6 +771,191 @@ pub struct SettleFlip<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct MixedRandomness<'info> {
+    /// CHECK: The account's data is validated manually within the handler.
+    pub randomness_account_data: AccountInfo<'info>,
+    /// CHECK: Address is checked against the SlotHashes sysvar id.
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitLootbox<'info> {
+    #[account(init,
+        payer = authority,
+        seeds = [b"lootboxConfig".as_ref()],
+        space = 8 + 32 + 2 * 4 + 1,
+        bump)]
+    pub lootbox_config: Account<'info, LootboxConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitLootboxTicket<'info> {
+    #[account(init,
+        payer = user,
+        seeds = [b"lootboxTicket".as_ref(), user.key().as_ref()],
+        space = 8 + 32 + 32 + 1 + 1 + 1,
+        bump)]
+    pub ticket: Account<'info, LootboxTicket>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommitLootbox<'info> {
+    #[account(mut,
+        seeds = [b"lootboxTicket".as_ref(), user.key().as_ref()],
+        bump = ticket.bump)]
+    pub ticket: Account<'info, LootboxTicket>,
+    pub user: Signer<'info>,
+    /// CHECK: The account's data is validated manually within the handler.
+    pub randomness_account_data: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenLootbox<'info> {
+    #[account(mut,
+        seeds = [b"lootboxTicket".as_ref(), user.key().as_ref()],
+        bump = ticket.bump)]
+    pub ticket: Account<'info, LootboxTicket>,
+    #[account(seeds = [b"lootboxConfig".as_ref()], bump = lootbox_config.bump)]
+    pub lootbox_config: Account<'info, LootboxConfig>,
+    pub user: Signer<'info>,
+    /// CHECK: The account's data is validated manually within the handler.
+    pub randomness_account_data: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitMap<'info> {
+    #[account(init,
+        payer = user,
+        seeds = [b"gameMap".as_ref(), user.key().as_ref()],
+        space = 8 + 32 + 32 + 1 + 32 + MAP_SIZE * MAP_SIZE + 1,
+        bump)]
+    pub map: Account<'info, GameMap>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommitMap<'info> {
+    #[account(mut,
+        seeds = [b"gameMap".as_ref(), user.key().as_ref()],
+        bump = map.bump)]
+    pub map: Account<'info, GameMap>,
+    pub user: Signer<'info>,
+    /// CHECK: The account's data is validated manually within the handler.
+    pub randomness_account_data: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GenerateMap<'info> {
+    #[account(mut,
+        seeds = [b"gameMap".as_ref(), user.key().as_ref()],
+        bump = map.bump)]
+    pub map: Account<'info, GameMap>,
+    pub user: Signer<'info>,
+    /// CHECK: The account's data is validated manually within the handler.
+    pub randomness_account_data: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleBatch<'info> {
+    /// CHECK: This is a simple Solana account holding SOL.
+    #[account(mut, seeds = [b"stateEscrow".as_ref()], bump)]
+    pub escrow_account: AccountInfo<'info>,
+    pub operator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitMatchQueue<'info> {
+    #[account(init,
+        payer = authority,
+        seeds = [b"matchQueue".as_ref(), authority.key().as_ref()],
+        space = 8 + 32 + 32 + 1
+            + 4 + 32 * MAX_QUEUE_PLAYERS
+            + 4 + 64 * (MAX_QUEUE_PLAYERS / 2)
+            + 1 + 32
+            + 1,
+        bump)]
+    pub match_queue: Account<'info, MatchQueue>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct JoinQueue<'info> {
+    #[account(mut,
+        seeds = [b"matchQueue".as_ref(), match_queue.authority.as_ref()],
+        bump = match_queue.bump)]
+    pub match_queue: Account<'info, MatchQueue>,
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CommitMatchmaking<'info> {
+    #[account(mut,
+        seeds = [b"matchQueue".as_ref(), authority.key().as_ref()],
+        bump = match_queue.bump)]
+    pub match_queue: Account<'info, MatchQueue>,
+    pub authority: Signer<'info>,
+    /// CHECK: The account's data is validated manually within the handler.
+    pub randomness_account_data: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Matchmake<'info> {
+    #[account(mut,
+        seeds = [b"matchQueue".as_ref(), match_queue.authority.as_ref()],
+        bump = match_queue.bump)]
+    pub match_queue: Account<'info, MatchQueue>,
+    /// CHECK: The account's data is validated manually within the handler.
+    pub randomness_account_data: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitDice<'info> {
+    #[account(init,
+        payer = user,
+        seeds = [b"diceGame".as_ref(), user.key().as_ref()],
+        space = 8 + 32 + 32 + 32 + 1 + 1 + 1,
+        bump)]
+    pub dice_game: Account<'info, DiceGame>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommitDice<'info> {
+    #[account(mut,
+        seeds = [b"diceGame".as_ref(), user.key().as_ref()],
+        bump = dice_game.bump)]
+    pub dice_game: Account<'info, DiceGame>,
+    pub user: Signer<'info>,
+    /// CHECK: The account's data is validated manually within the handler.
+    pub randomness_account_data: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RollDice<'info> {
+    #[account(mut,
+        seeds = [b"diceGame".as_ref(), user.key().as_ref()],
+        bump = dice_game.bump)]
+    pub dice_game: Account<'info, DiceGame>,
+    pub user: Signer<'info>,
+    /// CHECK: The account's data is validated manually within the handler.
+    pub randomness_account_data: AccountInfo<'info>,
+}
+
 // === Errors ===
 #[error_code]
 pub enum ErrorCode {
@@ -194,5 +965,339 @@ pub enum ErrorCode {
     NotEnoughFundsToPlay,
     RandomnessAlreadyRevealed,
     RandomnessNotResolved,
+    SlotHashesUnavailable,
+    InvalidLootboxWeights,
+    InvalidEscrowOwner,
+    RandomnessFromFutureSlot,
+    InvalidRandomnessQueue,
+    InvalidEscrowBump,
+    InvalidBatchAccounts,
+    InvalidRandomnessAccount,
+    AlreadyQueued,
+    QueueFull,
+    ClientSeedMismatch,
+    RandomnessAccountMismatch,
+    InvalidRandomnessOwner,
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mix_with_slot_hash_is_sha256_of_both_inputs() {
+        let revealed = [7u8; 32];
+        let slot_hash = [9u8; 32];
+        let mut preimage = [0u8; 64];
+        preimage[..32].copy_from_slice(&revealed);
+        preimage[32..].copy_from_slice(&slot_hash);
+        let expected = anchor_lang::solana_program::hash::hash(&preimage).to_bytes();
+        assert_eq!(mix_with_slot_hash(&revealed, &slot_hash), expected);
+        // Either input changing changes the result.
+        assert_ne!(mix_with_slot_hash(&revealed, &[0u8; 32]), expected);
+        assert_ne!(mix_with_slot_hash(&[0u8; 32], &slot_hash), expected);
+    }
+
+    #[test]
+    fn load_randomness_rejects_empty_and_short_accounts() {
+        let key = Pubkey::new_unique();
+        for len in [0usize, 8, 64] {
+            let mut lamports = 0u64;
+            let mut data = vec![0u8; len];
+            let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &ON_DEMAND_MAINNET_PID, false, 0);
+            assert_eq!(
+                load_randomness(&account).err().unwrap(),
+                Error::from(ErrorCode::InvalidRandomnessAccount)
+            );
+        }
+    }
+
+    #[test]
+    fn check_seed_slot_accepts_only_the_previous_slot() {
+        let cases = [
+            (100, 99, Ok(())),
+            (100, 100, Err(ErrorCode::RandomnessFromFutureSlot)),
+            (100, 101, Err(ErrorCode::RandomnessFromFutureSlot)),
+            (100, 98, Err(ErrorCode::RandomnessAlreadyRevealed)),
+            (100, 0, Err(ErrorCode::RandomnessAlreadyRevealed)),
+            (0, 0, Ok(())),
+            (0, 1, Err(ErrorCode::RandomnessFromFutureSlot)),
+        ];
+        for (slot, seed_slot, expected) in cases {
+            let clock = Clock { slot, ..Default::default() };
+            assert_eq!(
+                check_seed_slot(seed_slot, &clock),
+                expected.map_err(Error::from),
+                "slot {} seed_slot {}",
+                slot,
+                seed_slot
+            );
+        }
+    }
+
+    #[test]
+    fn check_randomness_queue_accepts_only_pinned_queues() {
+        for queue in ALLOWED_RANDOMNESS_QUEUES {
+            assert!(check_randomness_queue(queue).is_ok());
+        }
+        assert_eq!(
+            check_randomness_queue(&Pubkey::new_unique()).err().unwrap(),
+            Error::from(ErrorCode::InvalidRandomnessQueue)
+        );
+        assert!(check_randomness_queue(&Pubkey::default()).is_err());
+    }
+
+    fn roll(value: u64) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[..8].copy_from_slice(&value.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn rarity_from_randomness_respects_tier_boundaries() {
+        let weights = [6_000, 3_000, 900, 100];
+        let cases = [
+            (0, Rarity::Common),
+            (5_999, Rarity::Common),
+            (6_000, Rarity::Rare),
+            (8_999, Rarity::Rare),
+            (9_000, Rarity::Epic),
+            (9_899, Rarity::Epic),
+            (9_900, Rarity::Legendary),
+            (9_999, Rarity::Legendary),
+            // Rolls wrap modulo LOOTBOX_WEIGHT_TOTAL.
+            (10_000, Rarity::Common),
+            (16_000, Rarity::Rare),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(rarity_from_randomness(&roll(value), &weights), expected, "roll {}", value);
+        }
+        // A tier with zero weight is never rolled.
+        for value in [0, 5_000, 9_999] {
+            assert_eq!(rarity_from_randomness(&roll(value), &[0, 0, 0, 10_000]), Rarity::Legendary);
+        }
+    }
+
+    #[test]
+    fn generate_tiles_is_deterministic_per_seed() {
+        let seed = [42u8; 32];
+        let tiles = generate_tiles(&seed);
+        assert_eq!(tiles, generate_tiles(&seed));
+        assert_ne!(tiles, generate_tiles(&[43u8; 32]));
+
+        // Tile 0 is the roll of hash(seed || 0u16) against TILE_THRESHOLDS.
+        let hash = anchor_lang::solana_program::hash::hashv(&[&seed, &0u16.to_le_bytes()]).to_bytes();
+        let mut roll_bytes = [0u8; 8];
+        roll_bytes.copy_from_slice(&hash[..8]);
+        let roll = (u64::from_le_bytes(roll_bytes) % 100) as u8;
+        let expected = match roll {
+            0..=24 => TileType::Water,
+            25..=69 => TileType::Grass,
+            70..=89 => TileType::Forest,
+            _ => TileType::Mountain,
+        };
+        assert_eq!(tiles[0], expected);
+
+        // Across a handful of seeds every tile kind shows up.
+        let mut seen = [false; 4];
+        for byte in 0..16u8 {
+            for tile in generate_tiles(&[byte; 32]) {
+                seen[tile as usize] = true;
+            }
+        }
+        assert_eq!(seen, [true; 4]);
+    }
+
+    #[test]
+    fn pair_players_uses_every_player_exactly_once() {
+        for count in 0..=MAX_QUEUE_PLAYERS {
+            let players: Vec<Pubkey> = (0..count).map(|_| Pubkey::new_unique()).collect();
+            let seed = [count as u8; 32];
+            let (pairs, unmatched) = pair_players(&seed, &players);
+
+            assert_eq!(pairs.len(), count / 2);
+            assert_eq!(unmatched.is_some(), count % 2 == 1);
+            let mut placed: Vec<Pubkey> = pairs
+                .iter()
+                .flat_map(|pair| [pair.player_a, pair.player_b])
+                .chain(unmatched)
+                .collect();
+            placed.sort();
+            let mut expected = players.clone();
+            expected.sort();
+            assert_eq!(placed, expected, "{} players", count);
+
+            // The same seed reproduces the same pairing.
+            assert_eq!(pair_players(&seed, &players), (pairs, unmatched));
+        }
+    }
+
+    #[test]
+    fn pair_players_places_each_duplicate_entry_once() {
+        // join_queue rejects duplicates, but pair_players itself must neither
+        // drop nor invent entries when handed one: a repeated key is placed
+        // once per occurrence.
+        let a = Pubkey::new_unique();
+        let b = Pubkey::new_unique();
+        let players = vec![a, b, a];
+        let (pairs, unmatched) = pair_players(&[1u8; 32], &players);
+        assert_eq!(pairs.len(), 1);
+        let placed: Vec<Pubkey> = pairs
+            .iter()
+            .flat_map(|pair| [pair.player_a, pair.player_b])
+            .chain(unmatched)
+            .collect();
+        assert_eq!(placed.iter().filter(|key| **key == a).count(), 2);
+        assert_eq!(placed.iter().filter(|key| **key == b).count(), 1);
+    }
+
+    #[test]
+    fn reveal_committed_rejects_other_randomness_accounts() {
+        let committed = Pubkey::new_unique();
+        let mut player_state = PlayerState {
+            allowed_user: Pubkey::new_unique(),
+            latest_flip_result: false,
+            randomness_account: committed,
+            current_guess: true,
+            wager: 100,
+            bump: 255,
+        };
+        let other = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut lamports = 0u64;
+        let mut data = vec![0u8; 8 + std::mem::size_of::<RandomnessAccountData>()];
+        let account = AccountInfo::new(&other, false, false, &mut lamports, &mut data, &owner, false, 0);
+        let clock = Clock::default();
+        assert_eq!(
+            reveal_committed(&mut player_state, &account, &clock).err().unwrap(),
+            Error::from(ErrorCode::Unauthorized)
+        );
+        // A rejected settle leaves the commitment in place.
+        assert_eq!(player_state.randomness_account, committed);
+    }
+
+    #[test]
+    fn check_escrow_owner_requires_the_system_program() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 1_000_000u64;
+        let mut data = vec![];
+        let system_owner = anchor_lang::system_program::ID;
+        let escrow = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &system_owner, false, 0);
+        assert!(check_escrow_owner(&escrow).is_ok());
+
+        let mut lamports = 1_000_000u64;
+        let mut data = vec![];
+        let program_owner = crate::ID;
+        let escrow = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &program_owner, false, 0);
+        assert_eq!(
+            check_escrow_owner(&escrow).err().unwrap(),
+            Error::from(ErrorCode::InvalidEscrowOwner)
+        );
+    }
+
+    /// Account data for a randomness account: the Anchor discriminator
+    /// followed by a zeroed RandomnessAccountData with `queue` set.
+    fn randomness_data(queue: &Pubkey) -> Vec<u8> {
+        let mut data = vec![0u8; 8 + std::mem::size_of::<RandomnessAccountData>()];
+        let discriminator = anchor_lang::solana_program::hash::hash(b"account:RandomnessAccountData").to_bytes();
+        data[..8].copy_from_slice(&discriminator[..8]);
+        // `queue` follows the 32-byte authority.
+        data[8 + 32..8 + 64].copy_from_slice(queue.as_ref());
+        data
+    }
+
+    #[test]
+    fn load_randomness_requires_a_switchboard_owner() {
+        let key = Pubkey::new_unique();
+        let queue = ALLOWED_RANDOMNESS_QUEUES[0];
+
+        let mut lamports = 0u64;
+        let mut data = randomness_data(&queue);
+        let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &ON_DEMAND_DEVNET_PID, false, 0);
+        assert_eq!(load_randomness(&account).unwrap().queue, queue);
+
+        // Identical bytes in an account the attacker owns are rejected.
+        let attacker = Pubkey::new_unique();
+        let mut lamports = 0u64;
+        let mut data = randomness_data(&queue);
+        let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &attacker, false, 0);
+        assert_eq!(
+            load_randomness(&account).err().unwrap(),
+            Error::from(ErrorCode::InvalidRandomnessOwner)
+        );
+    }
+
+    #[test]
+    fn randomness_from_an_unpinned_queue_is_rejected() {
+        // A genuine Switchboard account on some other queue still fails the pin,
+        // so a player cannot use a queue they run as the dice server seed.
+        let key = Pubkey::new_unique();
+        let mut lamports = 0u64;
+        let mut data = randomness_data(&Pubkey::new_unique());
+        let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &ON_DEMAND_MAINNET_PID, false, 0);
+        let randomness = load_randomness(&account).unwrap();
+        assert_eq!(
+            check_randomness_queue(&randomness.queue).err().unwrap(),
+            Error::from(ErrorCode::InvalidRandomnessQueue)
+        );
+    }
+
+    #[test]
+    fn latest_slot_hash_reads_the_newest_entry_and_fails_closed() {
+        let key = anchor_lang::solana_program::sysvar::slot_hashes::ID;
+        let owner = anchor_lang::solana_program::sysvar::ID;
+
+        let mut lamports = 0u64;
+        let mut data = vec![0u8; 8 + 8 + 32 + 8 + 32];
+        data[..8].copy_from_slice(&2u64.to_le_bytes());
+        data[16..48].copy_from_slice(&[5u8; 32]);
+        data[56..88].copy_from_slice(&[6u8; 32]);
+        let slot_hashes = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert_eq!(latest_slot_hash(&slot_hashes).unwrap(), [5u8; 32]);
+
+        // An empty sysvar is an error rather than a zero hash, unless the
+        // test-only allow-missing-slothash mode is compiled in.
+        let mut lamports = 0u64;
+        let mut data = vec![0u8; 8];
+        let slot_hashes = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        if cfg!(feature = "allow-missing-slothash") {
+            assert_eq!(latest_slot_hash(&slot_hashes).unwrap(), [0u8; 32]);
+        } else {
+            assert_eq!(
+                latest_slot_hash(&slot_hashes).err().unwrap(),
+                Error::from(ErrorCode::SlotHashesUnavailable)
+            );
+        }
+    }
+
+    #[test]
+    fn missing_slot_hashes_mode_only_covers_an_empty_sysvar() {
+        // Even with the mode enabled, a populated sysvar is read as usual.
+        let key = anchor_lang::solana_program::sysvar::slot_hashes::ID;
+        let owner = anchor_lang::solana_program::sysvar::ID;
+        let mut lamports = 0u64;
+        let mut data = vec![0u8; 8 + 8 + 32];
+        data[..8].copy_from_slice(&1u64.to_le_bytes());
+        data[16..48].copy_from_slice(&[9u8; 32]);
+        let slot_hashes = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert_eq!(latest_slot_hash(&slot_hashes).unwrap(), [9u8; 32]);
+        assert_ne!(mix_with_slot_hash(&[1u8; 32], &[9u8; 32]), mix_with_slot_hash(&[1u8; 32], &[0u8; 32]));
+    }
+
+    #[test]
+    fn flip_outcome_is_a_function_of_revealed_value_and_user() {
+        let user = Pubkey::new_unique();
+        let mut heads = 0;
+        for byte in 0..=255u8 {
+            let revealed = [byte; 32];
+            let expected = anchor_lang::solana_program::hash::hashv(&[&revealed, user.as_ref()]).to_bytes()[0] % 2 == 0;
+            // settle_flip and settle_batch both call flip_outcome, so the same
+            // (revealed, user) always settles the same way on either path.
+            assert_eq!(flip_outcome(&revealed, &user), expected);
+            assert_eq!(flip_outcome(&revealed, &user), flip_outcome(&revealed, &user));
+            heads += expected as u32;
+        }
+        // Both outcomes occur; neither is fixed by the user key.
+        assert!(heads > 0 && heads < 256);
+    }
+}
@@ -1,16 +1,88 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { assert } from "chai";
 import { SbRandomness } from "../target/types/sb_randomness";
 
 describe("sb-randomness", () => {
   // Configure the client to use the local cluster.
-  anchor.setProvider(anchor.AnchorProvider.env());
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
 
   const program = anchor.workspace.SbRandomness as Program<SbRandomness>;
+  const user = provider.wallet.publicKey;
+  const [playerState] = PublicKey.findProgramAddressSync(
+    [Buffer.from("playerState"), user.toBuffer()],
+    program.programId
+  );
 
   it("Is initialized!", async () => {
-    // Add your test here.
+    // The randomness queue is pinned in the program, so initialize takes no arguments.
     const tx = await program.methods.initialize().rpc();
     console.log("Your transaction signature", tx);
+
+    const state = await program.account.playerState.fetch(playerState);
+    assert.ok(state.allowedUser.equals(user));
+    assert.ok(state.randomnessAccount.equals(PublicKey.default));
+  });
+
+  it("Rejects a dice commit whose randomness account does not match", async () => {
+    await program.methods.initDice().rpc();
+
+    // The key stored as the commitment differs from the account passed in.
+    const committed = Keypair.generate().publicKey;
+    const passed = Keypair.generate().publicKey;
+    try {
+      await program.methods
+        .commitDice(committed, Array(32).fill(0))
+        .accounts({ randomnessAccountData: passed })
+        .rpc();
+      assert.fail("commitDice should have failed");
+    } catch (e) {
+      assert.instanceOf(e, anchor.AnchorError);
+      assert.equal(
+        (e as anchor.AnchorError).error.errorCode.code,
+        "RandomnessAccountMismatch"
+      );
+    }
+  });
+
+  it("Rejects settle_flip with a non-canonical escrow bump", async () => {
+    const [, escrowBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("stateEscrow")],
+      program.programId
+    );
+    // The bump is checked before the randomness account, so any key will do.
+    try {
+      await program.methods
+        .settleFlip((escrowBump + 255) % 256)
+        .accounts({ randomnessAccountData: Keypair.generate().publicKey })
+        .rpc();
+      assert.fail("settleFlip should have failed");
+    } catch (e) {
+      assert.instanceOf(e, anchor.AnchorError);
+      assert.equal(
+        (e as anchor.AnchorError).error.errorCode.code,
+        "InvalidEscrowBump"
+      );
+    }
+  });
+
+  it("Rejects a dice commit whose randomness is not owned by Switchboard", async () => {
+    // The wallet is a system-owned account, so it cannot be randomness even
+    // when the committed and passed keys agree.
+    try {
+      await program.methods
+        .commitDice(user, Array(32).fill(0))
+        .accounts({ randomnessAccountData: user })
+        .rpc();
+      assert.fail("commitDice should have failed");
+    } catch (e) {
+      assert.instanceOf(e, anchor.AnchorError);
+      assert.equal(
+        (e as anchor.AnchorError).error.errorCode.code,
+        "InvalidRandomnessOwner"
+      );
+    }
   });
 });